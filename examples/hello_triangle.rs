@@ -1,22 +1,25 @@
 use std::time::Instant;
 
-use shimmer::prelude::*;
-use winit::dpi::PhysicalSize;
+use clap::Parser;
+use shimmer::{cli, prelude::*};
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    shimmer: cli::Args,
+}
 
 #[allow(clippy::unwrap_used)]
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
-    let program: Program<Uniforms<Sl>, sl::Vec2> = Program::new(
-        vertex_shader,
-        fragment_shader,
-        RunMode::Windowed(Some(WindowConfig {
-            title: "My window".into(),
-            size: PhysicalSize::new(800, 600),
-            draw_mode: shimmer::DrawMode::Loop { framerate: 144.0 },
-        })),
-    )?;
+    let mut args = Cli::parse().shimmer;
+    args.framerate.get_or_insert(144.0);
+    let program: Program<Uniforms<Sl>, sl::Vec2> =
+        Program::new(vertex_shader, fragment_shader, RunMode::from_args(&args))?;
     let start = Instant::now();
     let program = program
+        .with_args(&args)
+        .with_params::<Uniforms<Gl>>()
         .with_vertices(move |handle| {
             handle
                 .create_vertex_spec::<gl::Vec2>(
@@ -35,7 +38,7 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .create_uniform_binding::<Uniforms<Gl>>(
                     Uniforms {
                         time: Instant::now().duration_since(start).as_secs_f32(),
-                        size: 1.0,
+                        ..handle.param_block()
                     },
                     BufferUsage::StreamDraw,
                 )
@@ -50,10 +53,12 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 /// Define shader uniforms
-#[derive(Debug, Clone, Copy, Block)]
+#[derive(Debug, Clone, Copy, Block, Params)]
 #[repr(C)]
 struct Uniforms<D: BlockDom> {
+    #[param(skip)]
     time: D::F32,
+    #[param(range = 0.1..=2.0, default = 1.0)]
     size: D::F32,
 }
 
@@ -12,11 +12,15 @@ pub fn main() -> Result<(), Box<dyn Error + 'static>> {
             title: "My window".into(),
             size: PhysicalSize::new(800, 600),
             draw_mode: shimmer::DrawMode::Loop { framerate: 144.0 },
+            ..Default::default()
         })),
     )?;
-    // method not found in `Program<Uniforms<Sl>, Vec2>`
+    // error[E0277]: the program has no vertices to draw
+    // error[E0277]: the program's shaders take uniforms, but none are provided
+    // error[E0277]: the program has no draw settings
     //
-    // `serve` only exists on `Program` after `with_vertices`, `with_uniforms` (optionally), and `with_draw_settings` have been called
+    // `serve` needs `with_vertices`, `with_uniforms` (unless the shaders take
+    // none) and `with_draw_settings` to have been called first
     program.serve()?;
     Ok(())
 }
@@ -0,0 +1,70 @@
+use std::time::Instant;
+
+use posh::sl::ToSl;
+use shimmer::{
+    prelude::*,
+    utils::{
+        raymarch::{render, Camera},
+        sdf::{op_smooth_union, sd_plane, sd_sphere, sd_torus},
+    },
+};
+
+#[derive(Clone, Copy, Block)]
+#[repr(C)]
+struct Globals<D: BlockDom> {
+    time: D::F32,
+}
+
+#[derive(UniformInterface)]
+struct Uniforms<D: UniformInterfaceDom> {
+    app: D::Block<App<Sl>>,
+    globals: D::Block<Globals<Sl>>,
+}
+
+#[allow(clippy::unwrap_used)]
+fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+    let program = Program::<Uniforms<Sl>, _>::fullscreen(
+        fragment_shader,
+        RunMode::Windowed(Some(WindowConfig {
+            title: "Raymarching".into(),
+            draw_mode: shimmer::DrawMode::Loop { framerate: 60.0 },
+            ..Default::default()
+        })),
+    )?;
+    let start = Instant::now();
+    let program = program.with_uniforms(move |handle: Handle| Uniforms {
+        app: handle.app_buffer().unwrap(),
+        globals: handle
+            .create_uniform_binding::<Globals<Gl>>(
+                Globals {
+                    time: start.elapsed().as_secs_f32(),
+                },
+                BufferUsage::StreamDraw,
+            )
+            .unwrap(),
+    });
+    program.serve()?;
+    Ok(())
+}
+
+fn fragment_shader(Uniforms { app, globals }: Uniforms<Sl>, uv: sl::Vec2) -> sl::Vec4 {
+    let clip_space_pos = uv * 2.0 - 1.0;
+    let camera = Camera {
+        position: sl::Vec3::new(globals.time.sin() * 4.0, 2.0, globals.time.cos() * 4.0),
+        target: sl::Vec3::new(0.0, 0.5, 0.0),
+        fov: 1.0_f32.to_sl(),
+    };
+    // The distance function is all a scene needs
+    let scene = move |p: sl::Vec3| {
+        let sphere = sd_sphere(p - sl::Vec3::new(0.0, 1.0, 0.0), 0.8_f32.to_sl());
+        let torus = sd_torus(p - sl::Vec3::new(0.0, 0.3, 0.0), sl::Vec2::new(1.2, 0.2));
+        let floor = sd_plane(p, sl::Vec3::new(0.0, 1.0, 0.0), 0.0_f32.to_sl());
+        op_smooth_union(
+            op_smooth_union(sphere, torus, 0.3_f32.to_sl()),
+            floor,
+            0.1_f32.to_sl(),
+        )
+    };
+    render(&camera, clip_space_pos, app, scene)
+}
@@ -0,0 +1,222 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields, LitStr, Type};
+
+/// Derive `shimmer::params::ParamBlock` for a uniform block.
+///
+/// Generic blocks (`Uniforms<D: BlockDom>`) get an implementation for the
+/// `Gl` domain. Supported field types are `F32`, `Vec3`, `Vec4` and `Bool`
+/// (and their plain Rust counterparts). Fields are configured with the
+/// `#[param(...)]` attribute:
+///
+/// - `range = 0.0..=10.0`: slider range of a float field, `0.0..=1.0` by default.
+/// - `default = <expr>`: initial value of the field.
+/// - `name = "..."`: parameter name, the field name by default.
+/// - `skip`: do not expose the field, it is set to `Default::default()`.
+#[proc_macro_derive(Params, attributes(param))]
+pub fn derive_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Float,
+    Vec3,
+    Vec4,
+    Bool,
+}
+
+impl Kind {
+    fn of(ty: &Type) -> Option<Self> {
+        match ty {
+            Type::Path(path) => {
+                let ident = path.path.segments.last()?.ident.to_string();
+                match ident.as_str() {
+                    "F32" | "f32" => Some(Self::Float),
+                    "Vec3" => Some(Self::Vec3),
+                    "Vec4" => Some(Self::Vec4),
+                    "Bool" | "bool" => Some(Self::Bool),
+                    _ => None,
+                }
+            }
+            Type::Array(array) => match &array.len {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(len),
+                    ..
+                }) => match len.base10_digits() {
+                    "3" => Some(Self::Vec3),
+                    "4" => Some(Self::Vec4),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    name: Option<LitStr>,
+    range: Option<Expr>,
+    default: Option<Expr>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("param")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("name") {
+                    attrs.name = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("range") {
+                    attrs.range = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    attrs.default = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `skip`, `name`, `range` or `default`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attrs)
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "`Params` can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            input.span(),
+            "`Params` can only be derived for structs with named fields",
+        ));
+    };
+    let self_ty = match input.generics.type_params().count() {
+        0 => quote! { #ident },
+        1 => quote! { #ident<::shimmer::prelude::Gl> },
+        _ => {
+            return Err(syn::Error::new(
+                input.generics.span(),
+                "`Params` expects at most one generic parameter, the block domain",
+            ))
+        }
+    };
+
+    let mut params = Vec::new();
+    let mut from_params = Vec::new();
+    let mut write_params = Vec::new();
+    for field in &fields.named {
+        let attrs = FieldAttrs::parse(field)?;
+        let Some(field_ident) = &field.ident else {
+            continue;
+        };
+        if attrs.skip {
+            from_params.push(quote! { #field_ident: ::core::default::Default::default() });
+            continue;
+        }
+        let kind = Kind::of(&field.ty).ok_or_else(|| {
+            syn::Error::new(
+                field.ty.span(),
+                "unsupported parameter type, expected `F32`, `Vec3`, `Vec4` or `Bool`. \
+                 Use `#[param(skip)]` to leave this field out",
+            )
+        })?;
+        if attrs.range.is_some() && !matches!(kind, Kind::Float) {
+            return Err(syn::Error::new(
+                field.span(),
+                "`range` is only supported on float parameters",
+            ));
+        }
+        let name = attrs
+            .name
+            .unwrap_or_else(|| LitStr::new(&field_ident.to_string(), field_ident.span()));
+        let (param, default) = param_tokens(kind, &name, attrs.range, attrs.default);
+        params.push(param);
+        from_params.push(quote! {
+            #field_ident: params
+                .get(#name)
+                .or_else(|| ::shimmer::params::FromParam::from_param(&#default))
+                .unwrap_or_default()
+        });
+        write_params.push(quote! {
+            params.set(#name, ::shimmer::params::ToParam::to_param(self.#field_ident))?;
+        });
+    }
+
+    Ok(quote! {
+        impl ::shimmer::params::ParamBlock for #self_ty {
+            fn params() -> ::std::vec::Vec<::shimmer::params::Param> {
+                ::std::vec![#(#params),*]
+            }
+
+            #[allow(unused_variables)]
+            fn from_params(params: &::shimmer::params::Params) -> Self {
+                Self {
+                    #(#from_params),*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn write_params(
+                &self,
+                params: &mut ::shimmer::params::Params,
+            ) -> ::core::result::Result<(), ::shimmer::error::ErrKind> {
+                #(#write_params)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+/// Tokens for the `Param` description of a field and its default `ParamValue`.
+fn param_tokens(
+    kind: Kind,
+    name: &LitStr,
+    range: Option<Expr>,
+    default: Option<Expr>,
+) -> (TokenStream2, TokenStream2) {
+    match kind {
+        Kind::Float => {
+            let range = range.unwrap_or_else(|| syn::parse_quote!(0.0..=1.0));
+            let default = default.unwrap_or_else(|| syn::parse_quote!(*(#range).start()));
+            (
+                quote! { ::shimmer::params::Param::float(#name, #default, #range) },
+                quote! { ::shimmer::params::ParamValue::Float(#default) },
+            )
+        }
+        Kind::Vec3 => {
+            let default = default.unwrap_or_else(|| syn::parse_quote!([1.0, 1.0, 1.0]));
+            (
+                quote! { ::shimmer::params::Param::color3(#name, #default) },
+                quote! { ::shimmer::params::ParamValue::Vec3(#default) },
+            )
+        }
+        Kind::Vec4 => {
+            let default = default.unwrap_or_else(|| syn::parse_quote!([1.0, 1.0, 1.0, 1.0]));
+            (
+                quote! { ::shimmer::params::Param::color4(#name, #default) },
+                quote! { ::shimmer::params::ParamValue::Vec4(#default) },
+            )
+        }
+        Kind::Bool => {
+            let default = default.unwrap_or_else(|| syn::parse_quote!(false));
+            (
+                quote! { ::shimmer::params::Param::toggle(#name, #default) },
+                quote! { ::shimmer::params::ParamValue::Bool(#default) },
+            )
+        }
+    }
+}
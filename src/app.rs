@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use winit::event::WindowEvent;
+
+use crate::Handle;
+
+/// Hooks into the window loop of [`Program::run`](crate::Program::run), in
+/// the style of winit's `ApplicationHandler`. Every hook does nothing by
+/// default, and `()` is the handler used by
+/// [`Program::serve`](crate::Program::serve).
+pub trait AppHandler {
+    /// Called once, after the window is created and before the first frame.
+    fn setup(&mut self, _handle: Handle) {}
+
+    /// Called at the start of every frame, with how far
+    /// [`Handle::time`](crate::Handle::time) moved since the previous frame.
+    fn update(&mut self, _handle: Handle, _delta: Duration) {}
+
+    /// Called after the program is drawn and before the frame is presented,
+    /// to draw anything else with [`Handle::gl`].
+    fn draw(&mut self, _handle: Handle) {}
+
+    /// Called for every window event that the parameter overlay doesn't
+    /// consume.
+    fn event(&mut self, _handle: Handle, _event: &WindowEvent) {}
+}
+
+impl AppHandler for () {}
+
+impl<H: AppHandler + ?Sized> AppHandler for &mut H {
+    fn setup(&mut self, handle: Handle) {
+        (**self).setup(handle);
+    }
+
+    fn update(&mut self, handle: Handle, delta: Duration) {
+        (**self).update(handle, delta);
+    }
+
+    fn draw(&mut self, handle: Handle) {
+        (**self).draw(handle);
+    }
+
+    fn event(&mut self, handle: Handle, event: &WindowEvent) {
+        (**self).event(handle, event);
+    }
+}
@@ -0,0 +1,372 @@
+//! Audio analysis for music visualizers: the spectrum and waveform of a
+//! decoded audio file at the time of every frame, uploaded as a texture in
+//! the layout of Shadertoy's audio channels.
+//!
+//! Decoding and analysis don't need an audio device, so headless renders of
+//! visualizers work anywhere.
+
+use std::{ffi::OsStr, fs::File, io, path::Path, sync::Arc, time::Duration};
+
+use posh::{
+    gl::{self, Sampler2dSettings, TextureError},
+    sl::{self, ColorSampler2d, Vec2, F32},
+};
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::DecoderOptions,
+    errors::Error as DecodeError,
+    formats::FormatOptions,
+    io::{MediaSourceStream, MediaSourceStreamOptions},
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::error::{log_error, ErrKind};
+
+/// Width of the audio texture, the number of spectrum bins and waveform
+/// samples per frame.
+pub const AUDIO_TEXTURE_WIDTH: usize = 512;
+/// Samples per spectrum, giving [`AUDIO_TEXTURE_WIDTH`] bins.
+const FFT_SIZE: usize = 2 * AUDIO_TEXTURE_WIDTH;
+/// Decibels mapped to 0 and 1 in the spectrum, the defaults of the Web Audio
+/// `AnalyserNode`.
+const MIN_DECIBELS: f32 = -100.0;
+const MAX_DECIBELS: f32 = -30.0;
+
+/// A decoded audio file, mixed down to mono.
+#[derive(Clone)]
+pub struct AudioTrack {
+    samples: Arc<[f32]>,
+    sample_rate: u32,
+    fft: Arc<dyn Fft<f32>>,
+    /// Hann window applied before the FFT.
+    window: Vec<f32>,
+}
+
+impl AudioTrack {
+    /// Decode a WAV, Ogg Vorbis or MP3 file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be read, or
+    /// holds no audio track in a supported format.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ErrKind> {
+        let path = path.as_ref();
+        let (samples, sample_rate) = decode(path).map_err(|source| ErrKind::AudioError {
+            path: path.into(),
+            source,
+        })?;
+        let track = Self::from_samples(samples, sample_rate);
+        info!(
+            "Decoded {} at {} Hz, {:.1}s",
+            path.display(),
+            sample_rate,
+            track.duration().as_secs_f64()
+        );
+        Ok(track)
+    }
+
+    /// A track of mono `samples` in `[-1, 1]`.
+    #[must_use]
+    pub fn from_samples(samples: Vec<f32>, sample_rate: u32) -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                #[allow(clippy::cast_precision_loss)]
+                let x = i as f32 / FFT_SIZE as f32;
+                (std::f32::consts::TAU * x).cos().mul_add(-0.5, 0.5)
+            })
+            .collect();
+        Self {
+            samples: samples.into(),
+            sample_rate,
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+        }
+    }
+
+    #[must_use]
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The samples, shared with the player.
+    #[cfg(feature = "audio-playback")]
+    pub(crate) fn samples(&self) -> Arc<[f32]> {
+        self.samples.clone()
+    }
+
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        #[allow(clippy::cast_precision_loss)]
+        let samples = self.samples.len() as f64;
+        Duration::from_secs_f64(samples / f64::from(self.sample_rate.max(1)))
+    }
+
+    /// The spectrum and waveform of the samples just before `time`. Silent
+    /// outside the track.
+    // Sample indices and the constant sizes are far from the limits of the
+    // casts
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss
+    )]
+    #[must_use]
+    pub fn analyze(&self, time: Duration) -> AudioFrame {
+        let end = (time.as_secs_f64() * f64::from(self.sample_rate)) as i64;
+        let sample = |index: i64| {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| self.samples.get(index))
+                .copied()
+                .unwrap_or_default()
+        };
+        let start = end - FFT_SIZE as i64;
+        let mut buffer: Vec<_> = (start..end)
+            .zip(&self.window)
+            .map(|(index, window)| Complex::new(sample(index) * window, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+        let mut frame = AudioFrame::default();
+        for (bin, value) in frame.spectrum.iter_mut().zip(&buffer) {
+            let magnitude = value.norm() / FFT_SIZE as f32;
+            let decibels = 20.0 * magnitude.max(f32::MIN_POSITIVE).log10();
+            *bin = ((decibels - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)).clamp(0.0, 1.0);
+        }
+        let start = end - AUDIO_TEXTURE_WIDTH as i64;
+        for (value, index) in frame.waveform.iter_mut().zip(start..end) {
+            *value = sample(index).mul_add(0.5, 0.5).clamp(0.0, 1.0);
+        }
+        frame
+    }
+}
+
+/// The analysis of one frame of audio, every value within `[0, 1]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFrame {
+    /// Magnitude of the frequency bins from 0 Hz up to half the sample rate,
+    /// in decibels mapped from `[-100, -30]`.
+    pub spectrum: [f32; AUDIO_TEXTURE_WIDTH],
+    /// The most recent samples, mapped from `[-1, 1]`. Silence is 0.5.
+    pub waveform: [f32; AUDIO_TEXTURE_WIDTH],
+}
+
+impl Default for AudioFrame {
+    fn default() -> Self {
+        Self {
+            spectrum: [0.0; AUDIO_TEXTURE_WIDTH],
+            waveform: [0.5; AUDIO_TEXTURE_WIDTH],
+        }
+    }
+}
+
+impl AudioFrame {
+    /// Upload the frame as a 512×2 texture, the spectrum in the bottom row
+    /// and the waveform in the top row of every channel. Read it in shaders
+    /// with [`audio_spectrum`] and [`audio_waveform`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the texture creation fails.
+    pub fn create_sampler(
+        &self,
+        gl: &gl::Context,
+    ) -> Result<gl::ColorSampler2d<sl::Vec4>, TextureError> {
+        Ok(self
+            .create_texture(gl)?
+            .as_color_sampler(Sampler2dSettings::default()))
+    }
+
+    fn create_texture(
+        &self,
+        gl: &gl::Context,
+    ) -> Result<gl::ColorTexture2d<sl::Vec4>, TextureError> {
+        gl.create_color_texture_2d(Self::image(&self.texels()))
+    }
+
+    /// The RGBA bytes of the texture.
+    fn texels(&self) -> Vec<u8> {
+        let to_byte = |value: &f32| {
+            // Clamped to `[0, 1]`, so it fits
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let byte = (value * 255.0).round() as u8;
+            [byte; 4]
+        };
+        self.spectrum
+            .iter()
+            .chain(&self.waveform)
+            .flat_map(to_byte)
+            .collect()
+    }
+
+    fn image(texels: &[u8]) -> gl::ColorImage<'_> {
+        #[allow(clippy::cast_possible_truncation)]
+        gl::ColorImage::rgba_u8_slice([AUDIO_TEXTURE_WIDTH as u32, 2], texels)
+    }
+}
+
+/// An audio track with its analysis at the time of the frame being drawn,
+/// uploaded to one texture that is updated as the time moves.
+pub(crate) struct TrackAnalysis {
+    pub(crate) track: AudioTrack,
+    pub(crate) frame: AudioFrame,
+    texture: gl::ColorTexture2d<sl::Vec4>,
+}
+
+impl TrackAnalysis {
+    pub(crate) fn new(
+        gl: &gl::Context,
+        track: AudioTrack,
+        time: Duration,
+    ) -> Result<Self, ErrKind> {
+        let frame = track.analyze(time);
+        Ok(Self {
+            texture: frame.create_texture(gl)?,
+            track,
+            frame,
+        })
+    }
+
+    /// Analyze the track at `time` and upload the analysis.
+    pub(crate) fn set_time(&mut self, time: Duration) {
+        self.frame = self.track.analyze(time);
+        log_error(self.texture.set(0, AudioFrame::image(&self.frame.texels())));
+    }
+
+    pub(crate) fn sampler(&self) -> gl::ColorSampler2d<sl::Vec4> {
+        self.texture.as_color_sampler(Sampler2dSettings::default())
+    }
+}
+
+/// The spectrum of an audio texture at `x` in `[0, 1]`, from low to high
+/// frequencies.
+#[must_use]
+pub fn audio_spectrum(audio: ColorSampler2d<sl::Vec4>, x: F32) -> F32 {
+    audio.sample(Vec2::new(x, 0.25)).x
+}
+
+/// The waveform of an audio texture at `x` in `[0, 1]`, from old to new
+/// samples.
+#[must_use]
+pub fn audio_waveform(audio: ColorSampler2d<sl::Vec4>, x: F32) -> F32 {
+    audio.sample(Vec2::new(x, 0.75)).x
+}
+
+/// Decode the default track of the file at `path` into mono samples and
+/// their sample rate.
+fn decode(path: &Path) -> Result<(Vec<f32>, u32), DecodeError> {
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(OsStr::to_str) {
+        hint.with_extension(extension);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or(DecodeError::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or(DecodeError::Unsupported("unknown sample rate"))?;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let mut samples = Vec::new();
+    let mut buffer = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet only skips a few milliseconds
+            Err(DecodeError::DecodeError(err)) => {
+                warn!("Skipping undecodable audio packet: {}", err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        // Later packets may hold more samples than the first
+        let buffer = match &mut buffer {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * channels => buffer,
+            buffer => buffer.insert(SampleBuffer::<f32>::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        #[allow(clippy::cast_precision_loss)]
+        let scale = 1.0 / channels as f32;
+        samples.extend(
+            buffer
+                .samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() * scale),
+        );
+    }
+    Ok((samples, sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    /// One second of a quiet sine wave at `frequency`.
+    fn sine(frequency: f32) -> AudioTrack {
+        #[allow(clippy::cast_precision_loss)]
+        let samples = (0..SAMPLE_RATE)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                0.01 * (std::f32::consts::TAU * frequency * t).sin()
+            })
+            .collect();
+        AudioTrack::from_samples(samples, SAMPLE_RATE)
+    }
+
+    #[test]
+    fn sine_peaks_at_its_frequency() {
+        // Bins are 48000 / 1024 = 46.875 Hz apart
+        let frame = sine(32.0 * 46.875).analyze(Duration::from_millis(500));
+        let peak = frame
+            .spectrum
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(bin, _)| bin);
+        assert_eq!(peak, Some(32));
+    }
+
+    #[test]
+    fn silent_outside_the_track() {
+        let track = sine(1000.0);
+        for time in [Duration::ZERO, Duration::from_secs(2)] {
+            assert_eq!(track.analyze(time), AudioFrame::default());
+        }
+    }
+
+    #[test]
+    fn waveform_maps_samples_to_unit_range() {
+        let track = AudioTrack::from_samples(vec![-1.0, 0.0, 1.0, 2.0], 4);
+        let frame = track.analyze(Duration::from_secs(1));
+        let newest = &frame.waveform[AUDIO_TEXTURE_WIDTH - 4..];
+        assert_eq!(newest, [0.0, 0.5, 1.0, 1.0]);
+        assert!(frame.waveform[..AUDIO_TEXTURE_WIDTH - 4]
+            .iter()
+            .all(|&value| value == 0.5));
+    }
+}
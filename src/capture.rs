@@ -0,0 +1,94 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use image::RgbaImage;
+use posh::glow::{self, HasContext};
+use winit::dpi::PhysicalSize;
+
+use crate::error::ErrKind;
+
+/// Read the back buffer of the window into an image with its first row at
+/// the top.
+pub(crate) fn read_pixels(gl: &glow::Context, size: PhysicalSize<u32>) -> RgbaImage {
+    let mut image = RgbaImage::new(size.width, size.height);
+    let width = i32::try_from(size.width).unwrap_or(i32::MAX);
+    let height = i32::try_from(size.height).unwrap_or(i32::MAX);
+    unsafe {
+        // A draw into a texture may have left its framebuffer bound
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        gl.read_buffer(glow::BACK);
+        gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+        gl.read_pixels(
+            0,
+            0,
+            width,
+            height,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(&mut image),
+        );
+    }
+    // OpenGL's first row is the bottom one
+    image::imageops::flip_vertical_in_place(&mut image);
+    image
+}
+
+/// Read the floating point color buffer bound for reading into rows of RGBA
+/// values with the first row at the top.
+pub(crate) fn read_pixels_f32(gl: &glow::Context, size: PhysicalSize<u32>) -> Vec<f32> {
+    let row = size.width as usize * 4;
+    let mut bytes = vec![0u8; row * size.height as usize * 4];
+    let width = i32::try_from(size.width).unwrap_or(i32::MAX);
+    let height = i32::try_from(size.height).unwrap_or(i32::MAX);
+    unsafe {
+        gl.pixel_store_i32(glow::PACK_ALIGNMENT, 4);
+        gl.read_pixels(
+            0,
+            0,
+            width,
+            height,
+            glow::RGBA,
+            glow::FLOAT,
+            glow::PixelPackData::Slice(&mut bytes),
+        );
+    }
+    let values: Vec<f32> = bytes
+        .chunks_exact(4)
+        .map(|value| f32::from_ne_bytes([value[0], value[1], value[2], value[3]]))
+        .collect();
+    // OpenGL's first row is the bottom one
+    values
+        .chunks_exact(row.max(1))
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Save `image` to `path`, in the format of its extension.
+///
+/// # Errors
+///
+/// This function will return an error if the image can't be encoded or
+/// written.
+pub fn save_image(image: &RgbaImage, path: impl AsRef<Path>) -> Result<(), ErrKind> {
+    let path = path.as_ref();
+    image.save(path).map_err(|source| ErrKind::ImageError {
+        path: path.into(),
+        source,
+    })
+}
+
+/// Save `image` as `screenshot-<milliseconds since the epoch>.png` in the
+/// working directory, returning the path.
+pub(crate) fn save_screenshot(image: &RgbaImage) -> Result<PathBuf, ErrKind> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = PathBuf::from(format!("screenshot-{timestamp}.png"));
+    save_image(image, &path)?;
+    Ok(path)
+}
@@ -0,0 +1,269 @@
+use std::{path::PathBuf, time::Duration};
+
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    video::{is_video, VideoConfig},
+    DrawMode, RenderConfig, RunMode, WindowConfig,
+};
+
+/// Command-line flags shared by every shimmer program.
+///
+/// Flatten them into the program's own parser:
+///
+/// ```no_run
+/// use clap::Parser;
+/// use shimmer::{cli, RunMode};
+///
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[command(flatten)]
+///     shimmer: cli::Args,
+///     /// A flag of the program itself
+///     #[arg(long)]
+///     seed: Option<u64>,
+/// }
+///
+/// let cli = Cli::parse();
+/// let run_mode = RunMode::from_args(&cli.shimmer);
+/// ```
+///
+/// `--param` is applied by [`Program::with_args`](crate::Program::with_args),
+/// every other flag, `--preset` included, is read by [`RunMode::from_args`].
+/// Programs run in a window unless `--headless` is given, `--windowed` only
+/// states the default and can't be combined with `--headless`.
+#[derive(Debug, Clone, Default, PartialEq, clap::Args)]
+pub struct Args {
+    /// Render without opening a window
+    #[arg(long)]
+    pub headless: bool,
+    /// Render to a window, the default
+    #[arg(long, conflicts_with = "headless")]
+    pub windowed: bool,
+    /// Window or output size
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub size: Option<PhysicalSize<u32>>,
+    /// Size of the tiles a headless image is drawn in
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub tile_size: Option<PhysicalSize<u32>>,
+    /// Samples per pixel along each axis of a headless image, for
+    /// anti-aliasing
+    #[arg(long, value_name = "FACTOR")]
+    pub supersampling: Option<u32>,
+    /// Jittered sub-frames averaged into a headless image
+    #[arg(long, value_name = "FRAMES")]
+    pub accumulate: Option<u32>,
+    /// Redraw continuously at this framerate instead of drawing once, or the
+    /// framerate of headless animations
+    #[arg(long)]
+    pub framerate: Option<f32>,
+    /// Where to write rendered output
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    /// Number of frames to render
+    #[arg(long)]
+    pub frames: Option<u32>,
+    /// Video codec for ffmpeg, e.g. libx264. Encodes a video even if the
+    /// output isn't a .mp4, .mov, .mkv, .webm or .gif file
+    #[arg(long)]
+    pub codec: Option<String>,
+    /// Video bitrate for ffmpeg, e.g. 8M
+    #[arg(long)]
+    pub bitrate: Option<String>,
+    /// Duration of a seamless loop, driving the loop phase. Headless, exactly
+    /// one period is rendered
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub loop_duration: Option<Duration>,
+    /// Window title
+    #[arg(long)]
+    pub title: Option<String>,
+    /// Preset file to load parameter values from, and save them to
+    #[arg(long)]
+    pub preset: Option<PathBuf>,
+    /// Set a parameter, overriding the preset. Can be repeated
+    #[arg(long = "param", value_name = "NAME=VALUE")]
+    pub params: Vec<String>,
+}
+
+impl RunMode {
+    /// The run mode requested on the command line. Flags that are not given
+    /// keep the [`WindowConfig`] or [`RenderConfig`] defaults.
+    #[must_use]
+    pub fn from_args(args: &Args) -> Self {
+        if args.headless {
+            let default = RenderConfig::default();
+            let output = args.output.clone().unwrap_or(default.output);
+            let encode = is_video(&output) || args.codec.is_some() || args.bitrate.is_some();
+            let video = encode.then(|| VideoConfig {
+                codec: args.codec.clone(),
+                bitrate: args.bitrate.clone(),
+                ..VideoConfig::default()
+            });
+            return Self::Headless(Some(RenderConfig {
+                size: args.size.unwrap_or(default.size),
+                tile_size: args.tile_size.unwrap_or(default.tile_size),
+                supersampling: args.supersampling.unwrap_or(default.supersampling),
+                accumulate: args.accumulate.unwrap_or(default.accumulate),
+                frames: args.frames.unwrap_or(default.frames),
+                framerate: args.framerate.unwrap_or(default.framerate),
+                output,
+                video: video.or(default.video),
+                loop_duration: args.loop_duration.or(default.loop_duration),
+                preset: args.preset.clone().or(default.preset),
+            }));
+        }
+        let default = WindowConfig::default();
+        Self::Windowed(Some(WindowConfig {
+            title: args.title.clone().unwrap_or(default.title),
+            size: args.size.unwrap_or(default.size),
+            draw_mode: args
+                .framerate
+                .map_or(default.draw_mode, |framerate| DrawMode::Loop { framerate }),
+            preset: args.preset.clone().or(default.preset),
+            loop_duration: args.loop_duration.or(default.loop_duration),
+            ..default
+        }))
+    }
+}
+
+fn parse_size(size: &str) -> Result<PhysicalSize<u32>, String> {
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<u32>()
+            .map_err(|err| format!("invalid size `{size}`: {err}"))
+    };
+    let (width, height) = size
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got `{size}`"))?;
+    Ok(PhysicalSize::new(parse(width)?, parse(height)?))
+}
+
+fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    seconds
+        .trim()
+        .parse::<f64>()
+        .map_err(|err| err.to_string())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string()))
+        .map_err(|err| format!("invalid duration `{seconds}`: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headless(output: &str) -> Args {
+        Args {
+            headless: true,
+            output: Some(output.into()),
+            ..Args::default()
+        }
+    }
+
+    fn render_config(args: &Args) -> Option<RenderConfig> {
+        match RunMode::from_args(args) {
+            RunMode::Headless(config) => config,
+            RunMode::Windowed(_) => None,
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("800x600"), Ok(PhysicalSize::new(800, 600)));
+        assert_eq!(
+            parse_size(" 1920 X 1080 "),
+            Ok(PhysicalSize::new(1920, 1080))
+        );
+        for size in ["800", "800x", "x600", "800x600x2", "-1x600", "axb"] {
+            assert!(parse_size(size).is_err(), "`{size}` should not parse");
+        }
+    }
+
+    #[test]
+    fn parses_seconds() {
+        assert_eq!(parse_seconds("2.5"), Ok(Duration::from_millis(2500)));
+        assert_eq!(parse_seconds(" 4 "), Ok(Duration::from_secs(4)));
+        for seconds in ["", "-1", "ten", "inf"] {
+            assert!(
+                parse_seconds(seconds).is_err(),
+                "`{seconds}` should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn windowed_conflicts_with_headless() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            shimmer: Args,
+        }
+
+        let parse = |args: &[&str]| {
+            <Cli as clap::Parser>::try_parse_from(["shimmer"].iter().chain(args))
+                .map(|cli| cli.shimmer)
+        };
+        assert!(parse(&["--windowed"]).is_ok_and(|args| args.windowed));
+        assert!(parse(&["--headless"]).is_ok_and(|args| args.headless));
+        assert!(parse(&["--headless", "--windowed"]).is_err());
+    }
+
+    #[test]
+    fn runs_in_a_window_by_default() {
+        let args = Args {
+            windowed: true,
+            ..Args::default()
+        };
+        assert!(matches!(
+            RunMode::from_args(&args),
+            RunMode::Windowed(Some(WindowConfig {
+                draw_mode: DrawMode::Once,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn framerate_redraws_the_window_in_a_loop() {
+        let args = Args {
+            framerate: Some(60.0),
+            ..Args::default()
+        };
+        let framerate = match RunMode::from_args(&args) {
+            RunMode::Windowed(Some(WindowConfig {
+                draw_mode: DrawMode::Loop { framerate },
+                ..
+            })) => Some(framerate),
+            _ => None,
+        };
+        assert_eq!(framerate, Some(60.0));
+    }
+
+    #[test]
+    fn picks_video_from_the_output_extension() {
+        let video = render_config(&headless("out.MP4")).and_then(|config| config.video);
+        assert_eq!(video, Some(VideoConfig::default()));
+        let image = render_config(&headless("out.png"));
+        assert!(image.is_some_and(|config| config.video.is_none()));
+    }
+
+    #[test]
+    fn picks_video_from_codec_or_bitrate() {
+        let codec = Args {
+            codec: Some("libx264".into()),
+            ..headless("frames")
+        };
+        let video = render_config(&codec).and_then(|config| config.video);
+        assert_eq!(
+            video.and_then(|video| video.codec).as_deref(),
+            Some("libx264")
+        );
+
+        let bitrate = Args {
+            bitrate: Some("8M".into()),
+            ..headless("frames")
+        };
+        let video = render_config(&bitrate).and_then(|config| config.video);
+        assert_eq!(video.and_then(|video| video.bitrate).as_deref(), Some("8M"));
+    }
+}
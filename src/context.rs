@@ -0,0 +1,146 @@
+use glutin::config::{Api, Config, ConfigTemplateBuilder, GlConfig};
+pub use glutin::context::{ContextApi, GlProfile, Version};
+
+use crate::debug::DebugFilter;
+
+/// Options for creating the OpenGL context.
+///
+/// Context creation is attempted with `api` first and then with each of the
+/// `fallbacks` in order, until one succeeds.
+#[derive(Debug, Clone)]
+pub struct ContextConfig {
+    pub api: ContextApi,
+    /// Profile used for desktop OpenGL contexts. Ignored for OpenGL ES.
+    pub profile: GlProfile,
+    /// Request a debug context. With the `tracing` feature enabled, driver
+    /// debug messages are forwarded to `tracing`.
+    pub debug: bool,
+    pub debug_filter: DebugFilter,
+    pub fallbacks: Vec<ContextApi>,
+    pub framebuffer: FramebufferConfig,
+}
+
+/// Requested properties of the default framebuffer.
+///
+/// These are preferences: the config closest to them is picked, and the
+/// properties of the chosen config are available from
+/// [`Handle::framebuffer`](crate::Handle::framebuffer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramebufferConfig {
+    /// Number of MSAA samples, `0` disables multisampling.
+    pub samples: u8,
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+    /// Use an sRGB capable framebuffer and enable `GL_FRAMEBUFFER_SRGB`, so
+    /// linear shader output is gamma encoded on write.
+    pub srgb: bool,
+    /// Request an alpha channel and a transparent window.
+    pub alpha: bool,
+}
+
+impl Default for FramebufferConfig {
+    fn default() -> Self {
+        Self {
+            samples: 0,
+            depth_bits: 0,
+            stencil_bits: 0,
+            srgb: false,
+            alpha: true,
+        }
+    }
+}
+
+impl FramebufferConfig {
+    /// The properties of an existing config.
+    #[must_use]
+    pub fn of(config: &Config) -> Self {
+        Self {
+            samples: config.num_samples(),
+            depth_bits: config.depth_size(),
+            stencil_bits: config.stencil_size(),
+            srgb: config.srgb_capable(),
+            alpha: config.alpha_size() > 0 && config.supports_transparency() != Some(false),
+        }
+    }
+
+    /// How far `config` is from the requested properties, lower is better.
+    fn distance(&self, config: &Config) -> u32 {
+        let actual = Self::of(config);
+        let diff = |a: u8, b: u8| u32::from(a.abs_diff(b));
+        let mismatch = |a: bool, b: bool| if a == b { 0 } else { 16 };
+        diff(self.samples, actual.samples) * 4
+            + diff(self.depth_bits, actual.depth_bits) / 8
+            + diff(self.stencil_bits, actual.stencil_bits) / 8
+            + mismatch(self.srgb, actual.srgb)
+            + mismatch(self.alpha, actual.alpha)
+    }
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            api: ContextApi::OpenGl(Some(Version::new(4, 1))),
+            profile: GlProfile::Core,
+            debug: false,
+            debug_filter: DebugFilter::default(),
+            fallbacks: vec![
+                ContextApi::OpenGl(Some(Version::new(3, 3))),
+                ContextApi::Gles(Some(Version::new(3, 0))),
+            ],
+            framebuffer: FramebufferConfig::default(),
+        }
+    }
+}
+
+impl ContextConfig {
+    /// Every context api to try, in order of preference.
+    pub(crate) fn attempts(&self) -> impl Iterator<Item = ContextApi> + '_ {
+        std::iter::once(self.api).chain(self.fallbacks.iter().copied())
+    }
+
+    /// The config apis needed by the attempted contexts, each once.
+    pub(crate) fn config_apis(&self) -> Vec<Api> {
+        let mut apis = Vec::new();
+        for api in self.attempts().map(config_api_of) {
+            if !apis.contains(&api) {
+                apis.push(api);
+            }
+        }
+        apis
+    }
+
+    /// Pick the config closest to the requested framebuffer among the
+    /// `configs` that can create contexts of `api`.
+    ///
+    /// Returns `None` if none of them can.
+    pub(crate) fn pick_config(&self, api: ContextApi, configs: &[Config]) -> Option<Config> {
+        let api = config_api_of(api);
+        configs
+            .iter()
+            .filter(|config| config.api().contains(api))
+            .min_by_key(|config| self.framebuffer.distance(config))
+            .cloned()
+    }
+}
+
+/// The template for configs that support `api`. The framebuffer properties
+/// are left out, platforms take them as lower limits and would return no
+/// configs for more than the hardware has. [`ContextConfig::pick_config`]
+/// ranks the configs by them instead.
+pub(crate) fn config_template(api: Api) -> ConfigTemplateBuilder {
+    // glutin asks for 8 alpha, 24 depth and 8 stencil bits by default
+    ConfigTemplateBuilder::new()
+        .with_api(api)
+        .with_alpha_size(0)
+        .with_depth_size(0)
+        .with_stencil_size(0)
+}
+
+const fn config_api_of(api: ContextApi) -> Api {
+    match api {
+        ContextApi::OpenGl(_) => Api::OPENGL,
+        ContextApi::Gles(Some(Version { major: 1, .. })) => Api::GLES1,
+        ContextApi::Gles(Some(Version { major: 2, .. })) => Api::GLES2,
+        ContextApi::Gles(_) => Api::GLES3,
+    }
+}
@@ -0,0 +1,107 @@
+use posh::glow;
+#[cfg(feature = "tracing")]
+use posh::glow::HasContext;
+
+/// Severity of an OpenGL debug message, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Notification,
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    #[must_use]
+    pub const fn from_gl(severity: u32) -> Self {
+        match severity {
+            glow::DEBUG_SEVERITY_HIGH => Self::High,
+            glow::DEBUG_SEVERITY_MEDIUM => Self::Medium,
+            glow::DEBUG_SEVERITY_LOW => Self::Low,
+            _ => Self::Notification,
+        }
+    }
+}
+
+/// Which OpenGL debug messages are forwarded to `tracing`.
+///
+/// Only used with a debug context, see
+/// [`ContextConfig::debug`](crate::context::ContextConfig::debug).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugFilter {
+    /// Messages below this severity are dropped.
+    pub min_severity: DebugSeverity,
+    /// `GL_DEBUG_SOURCE_*` values to drop.
+    pub ignored_sources: Vec<u32>,
+    /// `GL_DEBUG_TYPE_*` values to drop.
+    pub ignored_types: Vec<u32>,
+}
+
+impl DebugFilter {
+    #[must_use]
+    pub fn accepts(&self, source: u32, kind: u32, severity: DebugSeverity) -> bool {
+        severity >= self.min_severity
+            && !self.ignored_sources.contains(&source)
+            && !self.ignored_types.contains(&kind)
+    }
+}
+
+/// Install a debug message callback that forwards driver messages to
+/// `tracing`. Does nothing if the context doesn't support debug output.
+#[cfg(feature = "tracing")]
+pub(crate) fn install(gl: &mut glow::Context, filter: DebugFilter) {
+    if !gl.supports_debug() {
+        tracing::warn!("Debug context requested, but debug output is not supported");
+        return;
+    }
+    unsafe {
+        gl.enable(glow::DEBUG_OUTPUT);
+        // Report messages from inside the call that caused them
+        gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl.debug_message_callback(move |source, kind, id, severity, message| {
+            let severity = DebugSeverity::from_gl(severity);
+            if !filter.accepts(source, kind, severity) {
+                return;
+            }
+            let span = tracing::info_span!(
+                "gl_debug",
+                source = source_name(source),
+                kind = type_name(kind),
+                id
+            );
+            let _enter = span.enter();
+            match severity {
+                DebugSeverity::High => tracing::error!("{}", message),
+                DebugSeverity::Medium => tracing::warn!("{}", message),
+                DebugSeverity::Low => tracing::info!("{}", message),
+                DebugSeverity::Notification => tracing::debug!("{}", message),
+            }
+        });
+    }
+}
+
+#[cfg(feature = "tracing")]
+const fn source_name(source: u32) -> &'static str {
+    match source {
+        glow::DEBUG_SOURCE_API => "api",
+        glow::DEBUG_SOURCE_WINDOW_SYSTEM => "window_system",
+        glow::DEBUG_SOURCE_SHADER_COMPILER => "shader_compiler",
+        glow::DEBUG_SOURCE_THIRD_PARTY => "third_party",
+        glow::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+#[cfg(feature = "tracing")]
+const fn type_name(kind: u32) -> &'static str {
+    match kind {
+        glow::DEBUG_TYPE_ERROR => "error",
+        glow::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated_behavior",
+        glow::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined_behavior",
+        glow::DEBUG_TYPE_PORTABILITY => "portability",
+        glow::DEBUG_TYPE_PERFORMANCE => "performance",
+        glow::DEBUG_TYPE_MARKER => "marker",
+        _ => "other",
+    }
+}
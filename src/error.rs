@@ -1,32 +1,175 @@
-use std::error::Error;
+use std::{error::Error, fmt, path::PathBuf, process::ExitStatus};
 
-use posh::gl::{BufferError, ContextError, DrawError, ProgramError};
-use winit::error::EventLoopError;
+use glutin::context::ContextApi;
+use posh::gl::{BufferError, ContextError, DrawError, ProgramError, TextureError};
+use winit::{
+    dpi::PhysicalSize,
+    error::{EventLoopError, OsError},
+};
+
+/// The step of program initialization that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitStep {
+    CreateDisplay,
+    CreateSurface,
+    MakeCurrent,
+}
+
+impl fmt::Display for InitStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::CreateDisplay => "create the display",
+            Self::CreateSurface => "create the window surface",
+            Self::MakeCurrent => "make the OpenGL context current",
+        })
+    }
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum ErrKind {
-    #[error("Display could not be created")]
-    DisplayError,
-    #[error("No OpenGL Config found for the current platform.")]
+    #[error("Failed to {step}")]
+    InitError {
+        step: InitStep,
+        #[source]
+        source: glutin::error::Error,
+    },
+    #[error("No OpenGL config found for the current platform")]
     NoConfigFound,
-    #[error("EventLoopError: {0}")]
+    #[error("Failed to create the event loop")]
     EventLoopError(#[from] EventLoopError),
-    #[error("Error creating window")]
-    WindowError,
-    #[error("OpenGl Error")]
-    OpenGlError(String),
-    #[error("PoshContextError: {0}")]
+    #[error("Failed to create the window")]
+    WindowError(#[source] OsError),
+    #[error("The program has no window to serve, draw it with `Program::draw_frame` instead")]
+    NoWindow,
+    #[error("Failed to create an OpenGL context, tried {}", format_attempts(.attempts))]
+    ContextCreationError {
+        attempts: Vec<(ContextApi, glutin::error::Error)>,
+    },
+    #[error("Failed to initialize the posh context")]
     PoshContextError(#[from] ContextError),
-    #[error("PoshProgramError: {0}")]
+    #[error("Failed to compile the shader program")]
     PoshProgramError(#[from] ProgramError),
-    #[error("PoshDrawError: {0}")]
+    #[error("Failed to draw")]
     PoshDrawError(#[from] DrawError),
-    #[error("PoshBufferError: {0}")]
+    #[error("Failed to create a buffer")]
     PoshBufferError(#[from] BufferError),
+    #[error("Failed to create a texture")]
+    PoshTextureError(#[from] TextureError),
+    #[error("Failed to set up reading back rendered tiles: {0}")]
+    ReadbackError(String),
+    #[error("Invalid parameter: {0}")]
+    InvalidParam(String),
+    #[error("Invalid render config: {0}")]
+    InvalidRenderConfig(String),
+    #[error(
+        "Tile size {}x{} holds no pixel at {supersampling}x supersampling",
+        .tile_size.width,
+        .tile_size.height
+    )]
+    InvalidTileSize {
+        tile_size: PhysicalSize<u32>,
+        supersampling: u32,
+    },
+    #[error("Failed to load or save preset `{}`", .path.display())]
+    PresetError {
+        path: PathBuf,
+        #[source]
+        source: PresetFileError,
+    },
+    #[error("Failed to save image `{}`", .path.display())]
+    ImageError {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("`{}` was not found, install ffmpeg to encode videos", .program.display())]
+    FfmpegNotFound { program: PathBuf },
+    #[error("Failed to encode video `{}`", .path.display())]
+    VideoError {
+        path: PathBuf,
+        #[source]
+        source: VideoFileError,
+    },
+    #[cfg(feature = "audio")]
+    #[error("Failed to decode audio `{}`", .path.display())]
+    AudioError {
+        path: PathBuf,
+        #[source]
+        source: symphonia::core::errors::Error,
+    },
+    #[error("Failed to save GPU trace `{}`", .path.display())]
+    ProfileError {
+        path: PathBuf,
+        #[source]
+        source: ProfileFileError,
+    },
+}
+
+/// The underlying cause of an [`ErrKind::PresetError`].
+#[derive(Debug, thiserror::Error)]
+pub enum PresetFileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    TomlDe(#[from] toml::de::Error),
+    #[error(transparent)]
+    TomlSer(#[from] toml::ser::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// The underlying cause of an [`ErrKind::ProfileError`].
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileFileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// The underlying cause of an [`ErrKind::VideoError`].
+#[derive(Debug, thiserror::Error)]
+pub enum VideoFileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("ffmpeg failed with {status}: {stderr}")]
+    Ffmpeg { status: ExitStatus, stderr: String },
+}
+
+impl ErrKind {
+    /// A multi-line, human readable report of the error and every error that
+    /// caused it, meant for printing to the terminal.
+    ///
+    /// ```text
+    /// error: Failed to create the window surface
+    ///
+    /// caused by:
+    ///     0: [3003] EGL_BAD_ALLOC
+    /// ```
+    #[must_use]
+    pub fn report(&self) -> String {
+        let mut report = format!("error: {self}");
+        let causes = std::iter::successors(self.source(), |e| e.source());
+        for (i, cause) in causes.enumerate() {
+            if i == 0 {
+                report.push_str("\n\ncaused by:");
+            }
+            report.push_str(&format!("\n    {i}: {cause}"));
+        }
+        report
+    }
+}
+
+fn format_attempts(attempts: &[(ContextApi, glutin::error::Error)]) -> String {
+    attempts
+        .iter()
+        .map(|(api, err)| format!("{api:?} ({err})"))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 pub(crate) fn log_error<T>(res: Result<T, impl Error>) {
     if let Err(e) = res {
-        tracing::error!("{}", e);
+        error!("{}", e);
     }
 }
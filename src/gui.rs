@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use egui_glow::EguiGlow;
+use glutin::display::{Display, GlDisplay};
+use posh::glow;
+use winit::{event::WindowEvent, event_loop::EventLoopWindowTarget, window::Window};
+
+use crate::params::{ParamKind, ParamValue, Params};
+
+/// Immediate mode overlay that draws the registered [`Params`] on top of the
+/// program output.
+pub struct Overlay {
+    egui: EguiGlow,
+}
+
+impl Overlay {
+    pub fn new<E>(event_loop: &EventLoopWindowTarget<E>, display: &Display) -> Self {
+        // egui_glow takes the context as an `Arc`, even though it is not
+        // `Send`, so the overlay loads a context of its own
+        #[allow(clippy::arc_with_non_send_sync)]
+        let gl = Arc::new(unsafe {
+            glow::Context::from_loader_function_cstr(|symbol| display.get_proc_address(symbol))
+        });
+        Self {
+            egui: EguiGlow::new(event_loop, gl, None, None),
+        }
+    }
+
+    /// Forward a window event to the overlay. Returns `true` if the overlay
+    /// consumed the event.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.egui.on_window_event(window, event).consumed
+    }
+
+    pub fn draw(&mut self, window: &Window, params: &mut Params) {
+        self.egui.run(window, |ctx| {
+            egui::Window::new("Parameters")
+                .default_open(true)
+                .resizable(false)
+                .show(ctx, |ui| params_ui(ui, params));
+        });
+        self.egui.paint(window);
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        self.egui.destroy();
+    }
+}
+
+fn params_ui(ui: &mut egui::Ui, params: &mut Params) {
+    for param in params.iter_mut() {
+        ui.horizontal(|ui| match (&param.kind, &mut param.value) {
+            (ParamKind::Slider(range), ParamValue::Float(value)) => {
+                ui.add(egui::Slider::new(value, range.clone()).text(param.name.as_str()));
+            }
+            (ParamKind::Checkbox, ParamValue::Bool(value)) => {
+                ui.checkbox(value, param.name.as_str());
+            }
+            (_, ParamValue::Vec3(value)) => {
+                ui.color_edit_button_rgb(value);
+                ui.label(param.name.as_str());
+            }
+            (_, ParamValue::Vec4(value)) => {
+                ui.color_edit_button_rgba_unmultiplied(value);
+                ui.label(param.name.as_str());
+            }
+            (_, value) => {
+                ui.label(format!("{}: {value:?}", param.name));
+            }
+        });
+    }
+    if ui.button("Reset").clicked() {
+        params.iter_mut().for_each(crate::params::Param::reset);
+    }
+}
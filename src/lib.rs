@@ -4,24 +4,33 @@
 #![feature(associated_type_defaults)]
 #![feature(trait_alias)]
 use std::{
-    error::Error,
-    marker::PhantomData,
-    process::exit,
+    ffi::{c_void, CStr},
+    path::PathBuf,
+    rc::Rc,
     time::{Duration, Instant},
 };
 
-use error::{log_error, ErrKind};
+use app::AppHandler;
+#[cfg(feature = "audio")]
+use audio::{AudioFrame, AudioTrack, TrackAnalysis};
+use context::{ContextApi, ContextConfig, FramebufferConfig};
+use error::{ErrKind, InitStep};
 use gl::Context;
 use glutin::{
-    config::{Api, Config, ConfigTemplateBuilder, GlConfig},
+    config::{Config, GlConfig},
     context::{
-        ContextApi, ContextAttributesBuilder, GlContext, GlProfile, NotCurrentGlContext,
-        PossiblyCurrentContext, PossiblyCurrentGlContext, Version,
+        ContextAttributesBuilder, GlContext, NotCurrentGlContext, PossiblyCurrentContext,
+        PossiblyCurrentGlContext,
     },
-    display::{GetGlDisplay, GlDisplay},
-    surface::{GlSurface, Surface, SurfaceAttributesBuilder, WindowSurface},
+    display::{Display, DisplayApiPreference, GetGlDisplay, GlDisplay},
+    surface::{Surface, SurfaceAttributesBuilder, WindowSurface},
 };
-use glutin_winit::{DisplayBuilder, GlWindow};
+use glutin_winit::GlWindow;
+use image::RgbaImage;
+use offline::Tile;
+use params::{Param, ParamBlock, Params};
+#[cfg(feature = "audio-playback")]
+use playback::Playback;
 use posh::{
     bytemuck::Pod,
     gl::{BufferError, BufferUsage, UniformBufferBinding},
@@ -36,122 +45,463 @@ use posh::{
     sl,
 };
 use prelude::utils::App;
-use raw_window_handle::HasRawWindowHandle;
+use preset::Preset;
+use profiler::{GpuProfiler, GpuScope, QueryResultFn};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use stage::{
+    Missing, Provided, SettingsCallback, SettingsStage, UniformStage, UniformsCallback,
+    VertexCallback, VertexStage,
+};
+use stats::{FrameStats, GpuTimer};
+use video::VideoConfig;
 use winit::{
-    dpi::PhysicalSize,
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    platform::pump_events::EventLoopExtPumpEvents,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::EventLoop,
+    keyboard::KeyCode,
     window::{Window, WindowBuilder},
 };
 
+#[macro_use]
+mod logging;
+pub mod app;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod capture;
+pub mod cli;
+pub mod context;
+pub mod debug;
 pub mod error;
+#[cfg(feature = "gui")]
+mod gui;
+pub mod offline;
+pub mod params;
+#[cfg(feature = "audio-playback")]
+pub mod playback;
 pub mod prelude;
+pub mod preset;
+pub mod profiler;
+pub mod stage;
+pub mod stats;
 pub mod utils;
+pub mod video;
+pub mod window;
 
-struct ProgramState {
+pub use shimmer_derive::Params;
+
+/// The window and context of a program that owns its window.
+struct WindowState {
     config: Config,
-    gl: gl::Context,
-    event_loop: EventLoop<()>,
+    /// Only the program that created the context owns the event loop, the
+    /// windows it opens share it.
+    event_loop: Option<EventLoop<()>>,
     gl_surface: Surface<WindowSurface>,
     window_builder: WindowBuilder,
     window: Window,
-    ctx: PossiblyCurrentContext,
+    /// Shared by every window of the event loop.
+    ctx: Rc<PossiblyCurrentContext>,
+    srgb: bool,
+}
+
+struct ProgramState {
+    gl: gl::Context,
+    /// `None` for programs embedded in a host application.
+    window: Option<WindowState>,
+    /// Size of the render target in pixels.
+    size: PhysicalSize<u32>,
+    params: Params,
+    framebuffer: FramebufferConfig,
+    /// Direct access to the context, for the timer queries and readback.
+    raw_gl: Rc<glow::Context>,
+    /// `None` if the driver can't read 64 bit query results.
+    query_result: Option<QueryResultFn>,
+    stats: FrameStats,
+    gpu_timer: Option<GpuTimer>,
+    profiler: Option<GpuProfiler>,
+    /// `name=value` assignments from the command line, applied on top of the
+    /// preset when serving.
+    param_overrides: Vec<String>,
+    /// Start of the previous frame.
+    last_frame: Option<Instant>,
+    /// Time of the frame being drawn, since the first frame.
+    time: Duration,
+    /// Position of the frame being drawn in the loop, if looping.
+    loop_phase: Option<f32>,
+    /// The audio track and its analysis at `time`.
+    #[cfg(feature = "audio")]
+    audio: Option<TrackAnalysis>,
+    /// The audio track playing through the system output, which drives the
+    /// frame clock.
+    #[cfg(feature = "audio-playback")]
+    playback: Option<Playback>,
+    /// The part of the image being drawn when rendering in tiles.
+    tile: Option<Tile>,
+    /// Sample offset of the sub-frame being accumulated, in pixels.
+    jitter: [f32; 2],
+    /// Where frames are drawn, the window unless rendering offline.
+    target: gl::Framebuffer<sl::Vec4>,
 }
 
 impl ProgramState {
     // FIXME: Improve error type
-    fn new(run_mode: RunMode) -> Result<Self, ErrKind> {
+    fn new(run_mode: RunMode, context_config: &ContextConfig) -> Result<Self, ErrKind> {
         let event_loop = EventLoop::new()?;
         let window_builder = WindowBuilder::new()
             .with_title("Posh")
-            .with_visible(!matches!(run_mode, RunMode::Headless))
-            .with_transparent(true);
+            .with_visible(!matches!(run_mode, RunMode::Headless(_)))
+            .with_transparent(context_config.framebuffer.alpha);
 
-        let window_builder =
-            if let RunMode::Windowed(Some(WindowConfig { title, size, .. })) = run_mode {
-                window_builder.with_inner_size(size)
-            } else {
-                window_builder
-            };
-
-        let template = ConfigTemplateBuilder::new().with_api(Api::OPENGL);
-        let display = DisplayBuilder::new().with_window_builder(Some(window_builder.clone()));
-        let (Some(window), config) = display
-            .build(&event_loop, template, |configs| {
-                let configs = configs.collect::<Vec<_>>();
-                let first = configs.first().cloned();
-                configs
-                    .into_iter()
-                    .find(|config| config.api() == Api::OPENGL)
-                    .or(first)
-                    .expect("No OpenGL config found")
-            })
-            .map_err(|_| ErrKind::DisplayError)?
-        else {
-            // DONE: return better error
-            return Err(ErrKind::WindowError);
+        let window_builder = match run_mode {
+            RunMode::Windowed(Some(WindowConfig { title, size, .. })) => {
+                window_builder.with_title(title).with_inner_size(size)
+            }
+            RunMode::Headless(_) | RunMode::Windowed(None) => window_builder,
         };
-        tracing::info!("Window {:?} created with config {:?}", window, config);
-        let raw_window_handle = window.raw_window_handle();
-        let context_attributes = ContextAttributesBuilder::new()
-            .with_profile(GlProfile::Core)
-            .with_context_api(ContextApi::OpenGl(Some(Version::new(4, 1))))
-            .build(Some(raw_window_handle));
-        let display = config.display();
+
+        let (display, window) = create_display(&event_loop, &window_builder)?;
         let version = display.version_string();
-        tracing::info!("OpenGL version: {:?}", version);
-        let ctx = unsafe {
-            display
-                .create_context(&config, &context_attributes)
-                .map_err(|_| ErrKind::OpenGlError("Context creation failed".into()))?
+        info!("OpenGL version: {:?}", version);
+        let configs = find_configs(&display, context_config, window.as_ref())?;
+        if configs.is_empty() {
+            return Err(ErrKind::NoConfigFound);
+        }
+        let raw_window_handle = window.as_ref().map(HasRawWindowHandle::raw_window_handle);
+        let mut attempts = Vec::new();
+        let created = context_config.attempts().find_map(|api| {
+            let config = context_config.pick_config(api, &configs)?;
+            let builder = ContextAttributesBuilder::new()
+                .with_debug(context_config.debug)
+                .with_context_api(api);
+            let builder = if let ContextApi::OpenGl(_) = api {
+                builder.with_profile(context_config.profile)
+            } else {
+                builder
+            };
+            let context_attributes = builder.build(raw_window_handle);
+            match unsafe { display.create_context(&config, &context_attributes) } {
+                Ok(ctx) => Some((config, ctx)),
+                Err(err) => {
+                    warn!("Failed to create {:?} context: {}", api, err);
+                    attempts.push((api, err));
+                    None
+                }
+            }
+        });
+        let Some((config, ctx)) = created else {
+            return Err(if attempts.is_empty() {
+                ErrKind::NoConfigFound
+            } else {
+                ErrKind::ContextCreationError { attempts }
+            });
         };
-        tracing::info!("OpenGL context created: {:?}", ctx.context_api());
-        let surface_attributes =
-            window.build_surface_attributes(SurfaceAttributesBuilder::default());
-        let gl_surface = unsafe {
-            config
-                .display()
-                .create_window_surface(&config, &surface_attributes)
-                .map_err(|_| ErrKind::OpenGlError("Failed to create gl surface".into()))?
+        info!("OpenGL context created: {:?}", ctx.context_api());
+        let window = match window {
+            Some(window) => window,
+            None => glutin_winit::finalize_window(&event_loop, window_builder.clone(), &config)
+                .map_err(ErrKind::WindowError)?,
         };
+        let framebuffer = FramebufferConfig::of(&config);
+        info!("Window {:?} created with config {:?}", window, config);
+        info!("Framebuffer: {:?}", framebuffer);
+        let srgb = context_config.framebuffer.srgb && framebuffer.srgb;
+        let gl_surface = create_surface(&config, &window, srgb)?;
         let ctx = ctx
             .make_current(&gl_surface)
-            .map_err(|_| ErrKind::OpenGlError("Failed to make context current".into()))?;
-        tracing::info!("Context made current: {:?}", ctx.is_current());
+            .map_err(|source| ErrKind::InitError {
+                step: InitStep::MakeCurrent,
+                source,
+            })?;
+        info!("Context made current: {:?}", ctx.is_current());
         let features = display.supported_features();
-        tracing::info!("Display features {:?}", features);
-        let gl = unsafe {
+        info!("Display features {:?}", features);
+        #[allow(unused_mut)]
+        let mut gl = unsafe {
             glow::Context::from_loader_function_cstr(|symbol| display.get_proc_address(symbol))
         };
+        #[cfg(feature = "tracing")]
+        if context_config.debug {
+            debug::install(&mut gl, context_config.debug_filter.clone());
+        }
+        // OpenGL ES has no switch, its sRGB surfaces always encode
+        if srgb && matches!(ctx.context_api(), ContextApi::OpenGl(_)) {
+            unsafe { glow::HasContext::enable(&gl, glow::FRAMEBUFFER_SRGB) };
+        }
         let gl = gl::Context::new(gl)?;
+        let raw_gl = Rc::new(unsafe {
+            glow::Context::from_loader_function_cstr(|symbol| display.get_proc_address(symbol))
+        });
+        let query_result = QueryResultFn::load(|symbol| display.get_proc_address(symbol));
+        let gpu_timer = GpuTimer::new(raw_gl.clone());
+        if gpu_timer.is_none() {
+            info!("Timer queries are not supported, GPU frame times are unavailable");
+        }
+        Ok(Self {
+            gl,
+            size: window.inner_size(),
+            window: Some(WindowState {
+                config,
+                event_loop: Some(event_loop),
+                gl_surface,
+                window_builder,
+                window,
+                ctx: Rc::new(ctx),
+                srgb,
+            }),
+            params: Params::default(),
+            framebuffer,
+            raw_gl,
+            query_result,
+            stats: FrameStats::default(),
+            gpu_timer,
+            profiler: None,
+            param_overrides: Vec::new(),
+            last_frame: None,
+            time: Duration::ZERO,
+            loop_phase: None,
+            #[cfg(feature = "audio")]
+            audio: None,
+            #[cfg(feature = "audio-playback")]
+            playback: None,
+            tile: None,
+            jitter: [0.0; 2],
+            target: gl::Framebuffer::default(),
+        })
+    }
+
+    /// State for another window sharing the context of this one.
+    fn sibling(&self, window: WindowState) -> Self {
+        let gpu_timer = GpuTimer::new(self.raw_gl.clone());
+        Self {
+            gl: self.gl.clone(),
+            size: window.window.inner_size(),
+            window: Some(window),
+            params: self.params.clone(),
+            framebuffer: self.framebuffer,
+            raw_gl: self.raw_gl.clone(),
+            query_result: self.query_result,
+            stats: FrameStats::default(),
+            gpu_timer,
+            profiler: None,
+            param_overrides: Vec::new(),
+            last_frame: None,
+            time: Duration::ZERO,
+            loop_phase: None,
+            #[cfg(feature = "audio")]
+            audio: None,
+            #[cfg(feature = "audio-playback")]
+            playback: None,
+            tile: None,
+            jitter: [0.0; 2],
+            target: gl::Framebuffer::default(),
+        }
+    }
+
+    /// State for a program drawing with a context owned by the host, loading
+    /// its functions with `load`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Program::embedded`].
+    unsafe fn embedded(
+        mut load: impl FnMut(&CStr) -> *const c_void,
+        size: PhysicalSize<u32>,
+    ) -> Result<Self, ErrKind> {
+        let gl = gl::Context::new(glow::Context::from_loader_function_cstr(&mut load))?;
+        let raw_gl = Rc::new(glow::Context::from_loader_function_cstr(&mut load));
+        let query_result = QueryResultFn::load(&mut load);
+        let gpu_timer = GpuTimer::new(raw_gl.clone());
         Ok(Self {
-            config,
             gl,
-            event_loop,
-            gl_surface,
-            window_builder,
-            window,
-            ctx,
+            window: None,
+            size,
+            params: Params::default(),
+            framebuffer: FramebufferConfig::default(),
+            raw_gl,
+            query_result,
+            stats: FrameStats::default(),
+            gpu_timer,
+            profiler: None,
+            param_overrides: Vec::new(),
+            last_frame: None,
+            time: Duration::ZERO,
+            loop_phase: None,
+            #[cfg(feature = "audio")]
+            audio: None,
+            #[cfg(feature = "audio-playback")]
+            playback: None,
+            tile: None,
+            jitter: [0.0; 2],
+            target: gl::Framebuffer::default(),
         })
     }
+
+    /// Load the preset and apply the command-line overrides.
+    fn load_params(&mut self, run_mode: &RunMode) -> Result<(), ErrKind> {
+        if let RunMode::Windowed(Some(WindowConfig {
+            preset: Some(path), ..
+        }))
+        | RunMode::Headless(Some(RenderConfig {
+            preset: Some(path), ..
+        })) = run_mode
+        {
+            // A preset that doesn't exist yet is created on the first save
+            if path.exists() {
+                Preset::load(path)?.apply(&mut self.params)?;
+            }
+        }
+        for assignment in &self.param_overrides {
+            self.params.apply_assignment(assignment)?;
+        }
+        Ok(())
+    }
+
+    /// Move the frame clock on by `delta`, or to the playback position while
+    /// the audio track is playing.
+    fn advance_time(&mut self, delta: Duration) {
+        #[cfg(feature = "audio-playback")]
+        if let Some(playback) = self.playback.as_ref() {
+            self.set_time(playback.position());
+            return;
+        }
+        self.set_time(self.time + delta);
+    }
+
+    /// Start playing the audio track, if there is one and an audio output.
+    #[cfg(feature = "audio-playback")]
+    fn start_playback(&mut self) {
+        let Some(audio) = self.audio.as_ref() else {
+            warn!("No audio track to play, add one with `Program::with_audio`");
+            return;
+        };
+        self.playback = Playback::start(&audio.track);
+    }
+
+    /// Move the frame clock to `time`, keeping the audio analysis in sync.
+    fn set_time(&mut self, time: Duration) {
+        self.time = time;
+        #[cfg(feature = "audio")]
+        if let Some(audio) = self.audio.as_mut() {
+            audio.set_time(time);
+        }
+    }
+
+    fn profile_begin(&mut self, name: &'static str) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.begin(name);
+        }
+    }
+
+    fn profile_end(&mut self) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.end();
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn create_overlay(&self, event_loop: &EventLoop<()>) -> Option<gui::Overlay> {
+        let display = self.window.as_ref()?.config.display();
+        Some(gui::Overlay::new(event_loop, &display))
+    }
 }
 
-pub struct WithVertices;
-pub struct WithUniforms;
-pub struct WithDrawSettings;
-pub struct WithoutVertices;
-pub struct WithoutUniforms;
-pub struct WithoutDrawSettings;
+fn create_surface(
+    config: &Config,
+    window: &Window,
+    srgb: bool,
+) -> Result<Surface<WindowSurface>, ErrKind> {
+    let surface_attributes =
+        window.build_surface_attributes(SurfaceAttributesBuilder::default().with_srgb(Some(srgb)));
+    unsafe {
+        config
+            .display()
+            .create_window_surface(config, &surface_attributes)
+            .map_err(|source| ErrKind::InitError {
+                step: InitStep::CreateSurface,
+                source,
+            })
+    }
+}
 
-pub struct Program<
-    U,
-    V,
-    F = sl::Vec4,
-    HasVertices = WithoutVertices,
-    HasUniforms = WithoutUniforms,
-    HasSettings = WithoutDrawSettings,
-> where
+/// Create the display the way glutin-winit does. On Windows the window is
+/// created along with it, since WGL needs one to load anything beyond
+/// OpenGL 1.1.
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn create_display(
+    event_loop: &EventLoop<()>,
+    window_builder: &WindowBuilder,
+) -> Result<(Display, Option<Window>), ErrKind> {
+    #[cfg(windows)]
+    let window = Some(
+        window_builder
+            .clone()
+            .build(event_loop)
+            .map_err(ErrKind::WindowError)?,
+    );
+    #[cfg(not(windows))]
+    let window = None;
+    #[cfg(windows)]
+    let preference = DisplayApiPreference::WglThenEgl(
+        window.as_ref().map(HasRawWindowHandle::raw_window_handle),
+    );
+    #[cfg(target_os = "macos")]
+    let preference = DisplayApiPreference::Cgl;
+    #[cfg(target_os = "android")]
+    let preference = DisplayApiPreference::Egl;
+    #[cfg(all(unix, not(target_os = "macos"), not(target_os = "android")))]
+    let preference =
+        DisplayApiPreference::EglThenGlx(Box::new(winit::platform::x11::register_xlib_error_hook));
+    let display =
+        unsafe { Display::new(event_loop.raw_display_handle(), preference) }.map_err(|source| {
+            ErrKind::InitError {
+                step: InitStep::CreateDisplay,
+                source,
+            }
+        })?;
+    Ok((display, window))
+}
+
+/// Every config that supports one of the attempted apis. Each api is queried
+/// on its own, since EGL only returns configs that support every api asked
+/// for at once.
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn find_configs(
+    display: &Display,
+    context_config: &ContextConfig,
+    window: Option<&Window>,
+) -> Result<Vec<Config>, ErrKind> {
+    let mut configs = Vec::new();
+    for api in context_config.config_apis() {
+        let template = context::config_template(api);
+        #[cfg(windows)]
+        let template = match window {
+            Some(window) => template.compatible_with_native_window(window.raw_window_handle()),
+            None => template,
+        };
+        match unsafe { display.find_configs(template.build()) } {
+            Ok(found) => configs.extend(found),
+            // No config supports this api
+            Err(err) if err.error_kind() == glutin::error::ErrorKind::BadConfig => {}
+            Err(source) => {
+                return Err(ErrKind::InitError {
+                    step: InitStep::CreateDisplay,
+                    source,
+                })
+            }
+        }
+    }
+    Ok(configs)
+}
+
+/// A shader program and the callbacks that feed it, served to a window or
+/// rendered headless.
+///
+/// Vertices, uniforms and draw settings are provided with
+/// [`with_vertices`](Program::with_vertices),
+/// [`with_uniforms`](Program::with_uniforms) and
+/// [`with_draw_settings`](Program::with_draw_settings), in any order. The
+/// last three generics track which of them have been provided, see
+/// [`stage`].
+pub struct Program<U, V, F = sl::Vec4, Vx = Missing, Un = Missing, St = Missing>
+where
     U: UniformInterface<Sl>,
     V: VsInterface<Sl>,
     F: ColorSample,
@@ -159,30 +509,31 @@ pub struct Program<
     state: ProgramState,
     run_mode: RunMode,
     inner: gl::Program<U, V, F>,
-    workflow: Workflow<U, V, F>,
-    _marker: PhantomData<(HasVertices, HasUniforms, HasSettings)>,
+    vertices: Vx,
+    uniforms: Un,
+    settings: St,
+    frame_stats: Option<FrameStatsCallback>,
 }
 
+/// A program built with [`Program::fullscreen`], with its vertices and draw
+/// settings already provided.
+pub type Fullscreen<U, F = sl::Vec4> = Program<
+    U,
+    sl::Vec2,
+    F,
+    Provided<VertexCallback<sl::Vec2>>,
+    Missing,
+    Provided<SettingsCallback>,
+>;
+
+#[derive(Clone, Copy)]
 pub struct Handle<'a>(&'a ProgramState);
 pub trait VertexFn<V: VsInterface<Sl>> = Fn(Handle) -> VertexSpec<V>;
 pub trait UniformsFn<U: UniformInterface<Sl>> = Fn(Handle) -> <U as UniformInterface<Sl>>::Gl;
 pub trait SettingsFn = Fn(Handle) -> gl::DrawSettings;
+pub trait FrameStatsFn = FnMut(&FrameStats);
 
-type VertexCallback<V> = Box<dyn VertexFn<V>>;
-type UniformsCallback<U> = Box<dyn UniformsFn<U>>;
-type SettingsCallback = Box<dyn SettingsFn>;
-
-pub struct Workflow<U, V, F>
-where
-    U: UniformInterface<Sl>,
-    V: VsInterface<Sl>,
-    F: ColorSample,
-{
-    vertex_spec: Option<VertexCallback<V>>,
-    uniforms: Option<UniformsCallback<U>>,
-    settings: Option<SettingsCallback>,
-    _marker: PhantomData<F>,
-}
+type FrameStatsCallback = Box<dyn FrameStatsFn>;
 
 impl<U: UniformInterface<Sl> + 'static, V: VsInterface<Sl> + 'static, F: ColorSample>
     Program<U, V, F>
@@ -213,116 +564,275 @@ impl<U: UniformInterface<Sl> + 'static, V: VsInterface<Sl> + 'static, F: ColorSa
         FFn: FsFunc<FSig>,
         U: UniformUnion<VSig::U, FSig::U>,
     {
-        let state = ProgramState::new(run_mode.clone())?;
+        Self::new_with_context(
+            vertex_shader,
+            fragment_shader,
+            run_mode,
+            &ContextConfig::default(),
+        )
+    }
+
+    /// Create a new program like [`Program::new`], with control over how the
+    /// OpenGL context is created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ErrKind::NoConfigFound`] if the platform has
+    /// no config for any of the requested apis, and an error if none of the
+    /// requested contexts could be created.
+    pub fn new_with_context<FFn, VFn, FSig, VSig>(
+        vertex_shader: VFn,
+        fragment_shader: FFn,
+        run_mode: RunMode,
+        context_config: &ContextConfig,
+    ) -> Result<Self, ErrKind>
+    where
+        VSig: VsSig<C = (), V = V>,
+        FSig: FsSig<C = (), W = VSig::W, F = F>,
+        VFn: VsFunc<VSig>,
+        FFn: FsFunc<FSig>,
+        U: UniformUnion<VSig::U, FSig::U>,
+    {
+        let state = ProgramState::new(run_mode.clone(), context_config)?;
         let inner: gl::Program<U, V, F> =
             state.gl.create_program(vertex_shader, fragment_shader)?;
         Ok(Self {
             state,
             run_mode,
             inner,
-            _marker: PhantomData,
-            workflow: Workflow {
-                vertex_spec: None,
-                uniforms: None,
-                settings: None,
-                _marker: PhantomData,
-            },
+            vertices: Missing,
+            uniforms: Missing,
+            settings: Missing,
+            frame_stats: None,
         })
     }
 
-    pub fn with_vertices(
-        self,
-        vertices: impl VertexFn<V> + 'static,
-    ) -> Program<U, V, F, WithVertices, WithoutUniforms, WithoutDrawSettings> {
-        Program {
-            workflow: Workflow {
-                vertex_spec: Some(Box::new(vertices)),
-                ..self.workflow
-            },
-            state: self.state,
-            run_mode: self.run_mode,
-            inner: self.inner,
-            _marker: PhantomData,
-        }
+    /// Create a program that draws with a context owned by a host application,
+    /// such as a game or another winit app, instead of opening its own window.
+    ///
+    /// The host calls [`Program::draw_frame`] from its own loop. `load` looks
+    /// up the OpenGL functions of the host's context, such as glutin's
+    /// `get_proc_address`. The parameter overlay is unavailable, as it needs
+    /// the event loop.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the posh context or the shader
+    /// program can't be created.
+    ///
+    /// # Safety
+    ///
+    /// The host's context must be current, and stay current whenever the
+    /// program is used, and `load` must return that context's functions.
+    pub unsafe fn embedded<FFn, VFn, FSig, VSig>(
+        vertex_shader: VFn,
+        fragment_shader: FFn,
+        load: impl FnMut(&CStr) -> *const c_void,
+        size: PhysicalSize<u32>,
+    ) -> Result<Self, ErrKind>
+    where
+        VSig: VsSig<C = (), V = V>,
+        FSig: FsSig<C = (), W = VSig::W, F = F>,
+        VFn: VsFunc<VSig>,
+        FFn: FsFunc<FSig>,
+        U: UniformUnion<VSig::U, FSig::U>,
+    {
+        let state = ProgramState::embedded(load, size)?;
+        let inner: gl::Program<U, V, F> =
+            state.gl.create_program(vertex_shader, fragment_shader)?;
+        Ok(Self {
+            state,
+            run_mode: RunMode::Windowed(None),
+            inner,
+            vertices: Missing,
+            uniforms: Missing,
+            settings: Missing,
+            frame_stats: None,
+        })
     }
+}
 
-    pub fn with_draw_settings(
+impl<U: UniformInterface<Sl> + 'static, F: ColorSample> Program<U, sl::Vec2, F> {
+    /// Create a program that runs `fragment_shader` over the whole window.
+    ///
+    /// The fragment shader receives the UV coordinate of the fragment, see
+    /// [`fullscreen_vertex_shader`](utils::fullscreen_vertex_shader). The
+    /// window is cleared to opaque black before drawing, which can be changed
+    /// with [`with_draw_settings`](Program::with_draw_settings).
+    ///
+    /// ```no_run
+    /// use shimmer::prelude::*;
+    ///
+    /// fn fragment_shader(_: (), uv: sl::Vec2) -> sl::Vec4 {
+    ///     sl::vec4(uv.x, uv.y, 0.5, 1.0)
+    /// }
+    ///
+    /// Program::<(), _>::fullscreen(fragment_shader, RunMode::Windowed(None))?.serve()?;
+    /// # Ok::<(), shimmer::error::ErrKind>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function can error if the program state fails to initialize, see
+    /// [`Program::new`], or the vertex buffer can't be created.
+    pub fn fullscreen<FFn, FSig>(
+        fragment_shader: FFn,
+        run_mode: RunMode,
+    ) -> Result<Fullscreen<U, F>, ErrKind>
+    where
+        FSig: FsSig<C = (), W = sl::Vec2, F = F>,
+        FFn: FsFunc<FSig>,
+        U: UniformUnion<(), FSig::U>,
+    {
+        let program = Self::new(utils::fullscreen_vertex_shader, fragment_shader, run_mode)?;
+        let quad = program
+            .state
+            .gl
+            .create_vertex_buffer(&utils::full_screen_quad(), BufferUsage::StaticDraw)?;
+        Ok(program
+            .with_vertices(move |_| quad.as_vertex_spec(PrimitiveMode::Triangles))
+            .with_draw_settings(|_| gl::DrawSettings {
+                clear_color: Some([0.0, 0.0, 0.0, 1.0]),
+                ..Default::default()
+            }))
+    }
+}
+
+impl<U, V, F, Vx, Un, St> Program<U, V, F, Vx, Un, St>
+where
+    U: UniformInterface<Sl> + 'static,
+    V: VsInterface<Sl> + 'static,
+    F: ColorSample,
+{
+    /// Provide the vertices drawn every frame.
+    pub fn with_vertices(
         self,
-        settings: impl SettingsFn + 'static,
-    ) -> Program<U, V, F, WithoutVertices, WithUniforms, WithoutDrawSettings> {
+        vertices: impl VertexFn<V> + 'static,
+    ) -> Program<U, V, F, Provided<VertexCallback<V>>, Un, St> {
         Program {
-            workflow: Workflow {
-                settings: Some(Box::new(settings)),
-                ..self.workflow
-            },
-            state: self.state,
-            run_mode: self.run_mode,
-            inner: self.inner,
-            _marker: PhantomData,
+            vertices: Provided(Box::new(vertices)),
+            ..self
         }
     }
-}
 
-impl<U: UniformInterface<Sl> + 'static, V: VsInterface<Sl> + 'static, F: ColorSample, US, DS>
-    Program<U, V, F, WithVertices, US, DS>
-{
+    /// Provide the uniforms for every draw. Not needed if the shaders take no
+    /// uniforms.
     pub fn with_uniforms(
         self,
         uniforms: impl UniformsFn<U> + 'static,
-    ) -> Program<U, V, F, WithVertices, WithUniforms, DS> {
+    ) -> Program<U, V, F, Vx, Provided<UniformsCallback<U>>, St> {
         Program {
-            workflow: Workflow {
-                uniforms: Some(Box::new(uniforms)),
-                ..self.workflow
-            },
-            state: self.state,
-            run_mode: self.run_mode,
-            inner: self.inner,
-            _marker: PhantomData,
+            uniforms: Provided(Box::new(uniforms)),
+            ..self
         }
     }
-}
 
-impl<U: UniformInterface<Sl> + 'static, V: VsInterface<Sl> + 'static, F: ColorSample, DS>
-    Program<U, V, F, WithVertices, WithUniforms, DS>
-{
+    /// Provide the draw settings for every draw.
     pub fn with_draw_settings(
         self,
         settings: impl SettingsFn + 'static,
-    ) -> Program<U, V, F, WithVertices, WithUniforms, WithDrawSettings> {
+    ) -> Program<U, V, F, Vx, Un, Provided<SettingsCallback>> {
         Program {
-            workflow: Workflow {
-                settings: Some(Box::new(settings)),
-                ..self.workflow
-            },
-            state: self.state,
-            run_mode: self.run_mode,
-            inner: self.inner,
-            _marker: PhantomData,
+            settings: Provided(Box::new(settings)),
+            ..self
+        }
+    }
+
+    /// Register a tweakable parameter. Its current value can be read from any
+    /// callback with [`Handle::params`]. With the `gui` feature enabled, the
+    /// parameters are shown in an overlay while running in windowed mode.
+    #[must_use]
+    pub fn with_param(mut self, param: Param) -> Self {
+        self.state.params.insert(param);
+        self
+    }
+
+    /// Register every parameter field of `B`, usually a block deriving
+    /// [`Params`](derive@Params). Read the block back in a callback with
+    /// [`Handle::param_block`].
+    #[must_use]
+    pub fn with_params<B: ParamBlock>(mut self) -> Self {
+        for param in B::params() {
+            self.state.params.insert(param);
+        }
+        self
+    }
+
+    /// Apply the `--param` assignments from the command line when serving,
+    /// after any preset is loaded. The other flags are read by
+    /// [`RunMode::from_args`].
+    #[must_use]
+    pub fn with_args(mut self, args: &cli::Args) -> Self {
+        self.state.param_overrides.clone_from(&args.params);
+        self
+    }
+
+    /// Time every frame and every draw on the GPU with timestamp queries. The
+    /// timings are emitted as `gpu_scope` tracing events as they arrive, and
+    /// written to `trace_path` as a Chrome trace JSON file, viewable in
+    /// Perfetto, when the program is dropped.
+    ///
+    /// Does nothing, apart from a warning, if the context doesn't support
+    /// timer queries.
+    #[must_use]
+    pub fn with_gpu_profiling(mut self, trace_path: impl Into<PathBuf>) -> Self {
+        let gl = self.state.raw_gl.clone();
+        self.state.profiler = self
+            .state
+            .query_result
+            .and_then(|query_result| GpuProfiler::new(gl, query_result, trace_path.into()));
+        if self.state.profiler.is_none() {
+            warn!("Timer queries are not supported, GPU profiling is disabled");
         }
+        self
+    }
+
+    /// Analyze `track` at the time of every frame, for music visualizers.
+    /// The analysis is uploaded to a texture for the shaders, see
+    /// [`Handle::audio_sampler`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the texture creation fails.
+    #[cfg(feature = "audio")]
+    pub fn with_audio(mut self, track: AudioTrack) -> Result<Self, ErrKind> {
+        self.state.audio = Some(TrackAnalysis::new(&self.state.gl, track, self.state.time)?);
+        Ok(self)
+    }
+
+    /// Call `callback` after every frame drawn in [`DrawMode::Loop`], with
+    /// the updated frame statistics. The same statistics are available from
+    /// any other callback with [`Handle::frame_stats`].
+    #[must_use]
+    pub fn on_frame_stats(mut self, callback: impl FrameStatsFn + 'static) -> Self {
+        self.frame_stats = Some(Box::new(callback));
+        self
     }
 }
 
-impl<U: UniformInterface<Sl> + 'static, V: VsInterface<Sl> + 'static>
-    Program<U, V, sl::Vec4, WithVertices, WithUniforms, WithDrawSettings>
+impl<U, V, Vx, Un, St> Program<U, V, sl::Vec4, Vx, Un, St>
+where
+    U: UniformInterface<Sl> + 'static,
+    V: VsInterface<Sl> + 'static,
 {
-    fn draw(&self) -> Result<(), ErrKind> {
-        match (
-            &self.workflow.vertex_spec,
-            &self.workflow.uniforms,
-            &self.workflow.settings,
-        ) {
-            (Some(vertex), Some(uniforms), Some(settings)) => {
-                self.inner
-                    .with_settings(settings(Handle(&self.state)))
-                    .with_uniforms(uniforms(Handle(&self.state)))
-                    .draw(vertex(Handle(&self.state)))?;
-            }
-            _ => {
-                unreachable!("Vertex spec, uniforms and settings must be provided")
-            }
-        }
-        Ok(())
+    fn draw(&mut self) -> Result<(), ErrKind>
+    where
+        Vx: VertexStage<V>,
+        Un: UniformStage<U>,
+        St: SettingsStage,
+    {
+        let settings = self.settings.settings(Handle(&self.state));
+        let vertices = self.vertices.vertex_spec(Handle(&self.state));
+        self.state.profile_begin("draw");
+        let drawn = self.uniforms.draw(
+            &self.inner,
+            Handle(&self.state),
+            self.state.target.clone(),
+            settings,
+            vertices,
+        );
+        self.state.profile_end();
+        Ok(drawn?)
     }
 
     /// Start the program, drawing to a window or running headless.
@@ -330,81 +840,114 @@ impl<U: UniformInterface<Sl> + 'static, V: VsInterface<Sl> + 'static>
     /// # Errors
     ///
     /// This function will return an error if drawing fails.
-    pub fn serve(self) -> Result<(), ErrKind> {
-        match self.run_mode {
-            RunMode::Headless => todo!("TODO: Implement headless mode"),
-            RunMode::Windowed(ref window_config) => {
-                let window_config = window_config.clone();
-                self.window_loop(&window_config)
+    pub fn serve(self) -> Result<(), ErrKind>
+    where
+        Vx: VertexStage<V>,
+        Un: UniformStage<U>,
+        St: SettingsStage,
+    {
+        self.run_with((), Vec::new())
+    }
+
+    /// Start the program like [`Program::serve`], calling the hooks of
+    /// `handler` from the window loop.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the event loop fails or drawing
+    /// fails.
+    pub fn run(self, handler: impl AppHandler) -> Result<(), ErrKind>
+    where
+        Vx: VertexStage<V>,
+        Un: UniformStage<U>,
+        St: SettingsStage,
+    {
+        self.run_with(handler, Vec::new())
+    }
+
+    fn record_frame(&mut self, frame_time: Duration, cpu_time: Duration) {
+        let state = &mut self.state;
+        state.stats.record(frame_time, cpu_time);
+        if let Some(timer) = state.gpu_timer.as_mut() {
+            for gpu_time in timer.poll() {
+                state.stats.record_gpu(gpu_time);
             }
         }
+        if let Some(callback) = self.frame_stats.as_mut() {
+            callback(&state.stats);
+        }
+        #[cfg(feature = "frame-stats")]
+        let _ = log_frame_stats(&state.stats);
     }
 
-    fn window_loop(mut self, window_config: &Option<WindowConfig>) -> Result<(), ErrKind> {
-        self.draw()?;
-        log_error(self.state.gl_surface.swap_buffers(&self.state.ctx));
-        loop {
-            if let Some(WindowConfig {
-                draw_mode: DrawMode::Loop { framerate },
-                ..
-            }) = window_config
-            {
-                let time = Instant::now();
-                let frame_time = Duration::from_secs_f32(1.0 / *framerate);
-                self.draw()?;
-                self.state.window.request_redraw();
-                log_error(self.state.gl_surface.swap_buffers(&self.state.ctx));
-                let delta = time.elapsed();
-                if delta < frame_time {
-                    std::thread::sleep(frame_time - delta);
-                }
-                #[cfg(feature = "tracing")]
-                let _ = log_frame_time(time.elapsed());
-            }
-            self.state
-                .event_loop
-                .pump_events(Some(Duration::ZERO), move |event, target| {
-                    target.set_control_flow(ControlFlow::Poll);
-                    if let Event::WindowEvent { event, .. } = event {
-                        match event {
-                            WindowEvent::CloseRequested => {
-                                exit(0);
-                            }
-                            WindowEvent::Resized(_) => {
-                                target.set_control_flow(ControlFlow::Poll);
-                            }
-                            _ => {}
-                        }
-                    }
-                });
+    /// Draw one frame, timed on the GPU.
+    fn render_frame(&mut self) -> Result<(), ErrKind>
+    where
+        Vx: VertexStage<V>,
+        Un: UniformStage<U>,
+        St: SettingsStage,
+    {
+        if let Some(timer) = self.state.gpu_timer.as_mut() {
+            timer.begin();
         }
+        self.state.profile_begin("frame");
+        let drawn = self.draw();
+        self.state.profile_end();
+        if let Some(timer) = self.state.gpu_timer.as_mut() {
+            timer.end();
+        }
+        drawn
     }
-}
 
-impl<V: VsInterface<Sl> + 'static>
-    Program<(), V, sl::Vec4, WithVertices, WithoutUniforms, WithDrawSettings>
-{
-    fn draw(&mut self) -> Result<(), ErrKind> {
-        match (
-            &self.workflow.vertex_spec,
-            &self.workflow.uniforms,
-            &self.workflow.settings,
-        ) {
-            (Some(vertex), None, Some(settings)) => {
-                self.inner
-                    .with_settings(settings(Handle(&self.state)))
-                    .draw(vertex(Handle(&self.state)))?;
-            }
-            _ => {
-                unreachable!("Vertex spec and settings must be provided")
-            }
+    /// Draw one frame into the host's context, for programs created with
+    /// [`Program::embedded`]. Call it from the host's own loop with the
+    /// current size of the render target; presenting the frame is left to the
+    /// host.
+    ///
+    /// The first call applies the `--param` assignments given with
+    /// [`Program::with_args`].
+    ///
+    /// The frame is drawn to the framebuffer bound by posh, the default one,
+    /// with the viewport set to `target`. posh caches the OpenGL state it
+    /// sets, such as the bound program, buffers, textures and framebuffer,
+    /// blending and depth testing, and skips setting what it believes is
+    /// already set. A host that changes any of that state with its own calls
+    /// has to restore it before the next `draw_frame`, or its changes leak
+    /// into this program's draws.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a parameter assignment is invalid
+    /// or drawing fails.
+    pub fn draw_frame(&mut self, target: PhysicalSize<u32>) -> Result<(), ErrKind>
+    where
+        Vx: VertexStage<V>,
+        Un: UniformStage<U>,
+        St: SettingsStage,
+    {
+        let time = Instant::now();
+        let last_frame = self.state.last_frame.replace(time);
+        match last_frame {
+            Some(last) => self.state.advance_time(time.duration_since(last)),
+            None => self.state.load_params(&self.run_mode)?,
         }
+        self.state.size = target;
+        let width = i32::try_from(target.width).unwrap_or(i32::MAX);
+        let height = i32::try_from(target.height).unwrap_or(i32::MAX);
+        unsafe { glow::HasContext::viewport(&*self.state.raw_gl, 0, 0, width, height) };
+        self.render_frame()?;
+        if let Some(profiler) = self.state.profiler.as_mut() {
+            profiler.end_frame();
+        }
+        let cpu_time = time.elapsed();
+        let frame_time = last_frame.map_or(cpu_time, |last| time.duration_since(last));
+        self.record_frame(frame_time, cpu_time);
         Ok(())
     }
 }
 
-#[cfg(feature = "tracing")]
-fn log_frame_time(time: Duration) -> Result<(), Box<dyn Error + 'static>> {
+#[cfg(feature = "frame-stats")]
+fn log_frame_stats(stats: &FrameStats) -> Result<(), Box<dyn std::error::Error + 'static>> {
     use std::io::stdout;
 
     use crossterm::{
@@ -413,18 +956,53 @@ fn log_frame_time(time: Duration) -> Result<(), Box<dyn Error + 'static>> {
         terminal::{Clear, ClearType},
     };
 
-    let ms = time.as_millis();
+    let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+    let gpu = stats
+        .gpu_time()
+        .map_or_else(|| "n/a".to_owned(), |time| format!("{:.2}ms", ms(time)));
     execute!(stdout(), Clear(ClearType::CurrentLine))?;
-    tracing::info!(name: "frame_time", "Frame time: {:.2}ms\t FPS: {:.2}", ms, 1.0 / time.as_secs_f64());
+    tracing::info!(
+        name: "frame_time",
+        "Frame time: {:.2}ms (min {:.2}ms, max {:.2}ms, p99 {:.2}ms)\t FPS: {:.1}\t CPU: {:.2}ms\t GPU: {}",
+        ms(stats.average()),
+        ms(stats.min()),
+        ms(stats.max()),
+        ms(stats.percentile(99)),
+        stats.fps(),
+        ms(stats.cpu_time()),
+        gpu
+    );
     execute!(stdout(), MoveUp(1))?;
     Ok(())
 }
 
+/// Key that saves the current parameter values to the preset file.
+pub const SAVE_PRESET_KEY: KeyCode = KeyCode::F5;
+/// Default for [`WindowConfig::screenshot_key`].
+pub const DEFAULT_SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+const DEFAULT_PRESET_PATH: &str = "preset.toml";
+
 #[derive(Debug, Clone)]
 pub struct WindowConfig {
+    /// Title of the window. Windows of programs created with
+    /// `RunMode::Windowed(None)` are titled `Posh`.
     pub title: String,
     pub size: PhysicalSize<u32>,
     pub draw_mode: DrawMode,
+    /// Preset file loaded at startup and written when [`SAVE_PRESET_KEY`] is
+    /// pressed. Defaults to `preset.toml` in the working directory when saving.
+    pub preset: Option<PathBuf>,
+    /// Key that saves the window contents, without the parameter overlay, as
+    /// a timestamped PNG in the working directory. `None` disables it.
+    pub screenshot_key: Option<KeyCode>,
+    /// Duration of the loop [`Handle::loop_phase`] follows, to preview loops
+    /// exported with [`RenderConfig::loop_duration`].
+    pub loop_duration: Option<Duration>,
+    /// Play the track given with [`Program::with_audio`] through the system
+    /// output and lock [`Handle::time`] to its playback position. Without an
+    /// audio output the track is analyzed on the frame clock as usual.
+    #[cfg(feature = "audio-playback")]
+    pub play_audio: bool,
 }
 
 impl Default for WindowConfig {
@@ -433,6 +1011,67 @@ impl Default for WindowConfig {
             title: "Shimmer".into(),
             size: PhysicalSize::new(800, 600),
             draw_mode: DrawMode::Once,
+            preset: None,
+            screenshot_key: Some(DEFAULT_SCREENSHOT_KEY),
+            loop_duration: None,
+            #[cfg(feature = "audio-playback")]
+            play_audio: false,
+        }
+    }
+}
+
+/// Default for [`RenderConfig::tile_size`].
+pub const DEFAULT_TILE_SIZE: PhysicalSize<u32> = PhysicalSize::new(1024, 1024);
+
+/// How a headless program renders its image.
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    /// Size of the rendered image.
+    pub size: PhysicalSize<u32>,
+    /// Size of the textures the image is drawn to, one tile at a time,
+    /// rounded down to a multiple of `supersampling`. Keep it within the
+    /// maximum texture and viewport size of the GPU.
+    pub tile_size: PhysicalSize<u32>,
+    /// Draw `supersampling`×`supersampling` samples for every pixel and
+    /// average them, for anti-aliasing. 1 draws one sample per pixel.
+    pub supersampling: u32,
+    /// Draw every tile this many times with the samples jittered within the
+    /// pixels, see [`Handle::jitter`], and average the sub-frames. Gives
+    /// clean stills of noisy shaders, like path tracers, and anti-aliasing
+    /// without the memory cost of supersampling. 1 draws a single frame.
+    pub accumulate: u32,
+    /// Number of frames to render. Ignored when `loop_duration` is set.
+    pub frames: u32,
+    /// Frames per second of the rendered animation, which sets
+    /// [`Handle::time`] of every frame.
+    pub framerate: f32,
+    /// Where the image is written, in the format of its extension. Several
+    /// frames are written as a numbered sequence, `render-0000.png`,
+    /// `render-0001.png` and so on, unless they are encoded as a video.
+    pub output: PathBuf,
+    /// Encode the frames into a video at `output` with `ffmpeg`.
+    pub video: Option<VideoConfig>,
+    /// Render one period of a loop of this duration, rounded to whole
+    /// frames. [`Handle::loop_phase`] of frame `i` of `n` is exactly `i / n`,
+    /// so the frame after the last one would be the first one again.
+    pub loop_duration: Option<Duration>,
+    /// Preset file to load parameter values from.
+    pub preset: Option<PathBuf>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            size: PhysicalSize::new(1920, 1080),
+            tile_size: DEFAULT_TILE_SIZE,
+            supersampling: 1,
+            accumulate: 1,
+            frames: 1,
+            framerate: 30.0,
+            output: "render.png".into(),
+            video: None,
+            loop_duration: None,
+            preset: None,
         }
     }
 }
@@ -448,7 +1087,7 @@ pub enum DrawMode {
 
 #[derive(Debug, Clone)]
 pub enum RunMode {
-    Headless,
+    Headless(Option<RenderConfig>),
     Windowed(Option<WindowConfig>),
 }
 
@@ -457,16 +1096,132 @@ impl<'a> Handle<'a> {
     pub const fn gl(&self) -> &gl::Context {
         &self.0.gl
     }
+    /// The parameters registered with [`Program::with_param`].
+    #[must_use]
+    pub const fn params(&self) -> &Params {
+        &self.0.params
+    }
+    /// Every GPU timing collected so far, empty unless profiling was enabled
+    /// with [`Program::with_gpu_profiling`].
+    #[must_use]
+    pub fn gpu_scopes(&self) -> &[GpuScope] {
+        self.0
+            .profiler
+            .as_ref()
+            .map(GpuProfiler::scopes)
+            .unwrap_or_default()
+    }
+    /// Read back what has been drawn to the window so far this frame, e.g.
+    /// from [`AppHandler::draw`] for the finished frame.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ErrKind::NoWindow`] for programs created
+    /// with [`Program::embedded`], whose frames go wherever the host has them
+    /// drawn.
+    pub fn capture_frame(&self) -> Result<RgbaImage, ErrKind> {
+        if self.0.window.is_none() {
+            return Err(ErrKind::NoWindow);
+        }
+        Ok(capture::read_pixels(&self.0.raw_gl, self.0.size))
+    }
+    /// Timing statistics over the most recent frames.
+    #[must_use]
+    pub const fn frame_stats(&self) -> &FrameStats {
+        &self.0.stats
+    }
+    /// The properties of the framebuffer that was picked for the window.
+    #[must_use]
+    pub const fn framebuffer(&self) -> FramebufferConfig {
+        self.0.framebuffer
+    }
+    /// Build `B` from the current parameter values.
+    #[must_use]
+    pub fn param_block<B: ParamBlock>(&self) -> B {
+        B::from_params(&self.0.params)
+    }
+    /// The per-frame data for the [`App`] block. When rendering in tiles
+    /// with [`Program::render_image`], `size` is the size of the whole image.
     #[must_use]
     pub fn app(&self) -> App<Gl> {
-        let size = self.0.window.inner_size();
+        let tile_size = self.0.size;
+        let (size, offset) = match self.0.tile {
+            Some(tile) => (tile.image_size, tile.offset),
+            None => (tile_size, PhysicalPosition::new(0, 0)),
+        };
         App {
             size: gl::UVec2 {
                 x: size.width,
                 y: size.height,
             },
+            tile_offset: gl::UVec2 {
+                x: offset.x,
+                y: offset.y,
+            },
+            tile_size: gl::UVec2 {
+                x: tile_size.width,
+                y: tile_size.height,
+            },
+            jitter: gl::Vec2 {
+                x: self.0.jitter[0],
+                y: self.0.jitter[1],
+            },
+            time: self.time().as_secs_f32(),
+            loop_phase: self.loop_phase().unwrap_or_default(),
         }
     }
+    /// Time of the frame being drawn since the first frame. Headless frames
+    /// are spaced evenly at [`RenderConfig::framerate`], so animations that
+    /// follow it render the same however long each frame takes.
+    #[must_use]
+    pub const fn time(&self) -> Duration {
+        self.0.time
+    }
+    /// Position of the frame being drawn in the loop, within `[0, 1)`, when
+    /// a loop duration is set with [`RenderConfig::loop_duration`] or
+    /// [`WindowConfig::loop_duration`]. Animations driven by it alone loop
+    /// seamlessly.
+    #[must_use]
+    pub const fn loop_phase(&self) -> Option<f32> {
+        self.0.loop_phase
+    }
+    /// The analysis of the audio track given with [`Program::with_audio`] at
+    /// the time of the frame being drawn.
+    #[cfg(feature = "audio")]
+    #[must_use]
+    pub fn audio(&self) -> Option<&AudioFrame> {
+        self.0.audio.as_ref().map(|audio| &audio.frame)
+    }
+    /// [`Handle::audio`] as a 512×2 texture for the shaders, see
+    /// [`AudioFrame::create_sampler`]. The texture is updated every frame.
+    #[cfg(feature = "audio")]
+    #[must_use]
+    pub fn audio_sampler(&self) -> Option<gl::ColorSampler2d<sl::Vec4>> {
+        self.0.audio.as_ref().map(TrackAnalysis::sampler)
+    }
+    /// The audio track playing alongside the window, see
+    /// [`WindowConfig::play_audio`]. Pause and seek it here to move the
+    /// visuals along.
+    #[cfg(feature = "audio-playback")]
+    #[must_use]
+    pub const fn playback(&self) -> Option<&Playback> {
+        self.0.playback.as_ref()
+    }
+    /// Offset of the samples of the sub-frame being drawn from the pixel
+    /// centers, in pixels, within `[-0.5, 0.5)` on both axes. It changes for
+    /// every sub-frame accumulated with [`RenderConfig::accumulate`] and is
+    /// zero otherwise.
+    #[must_use]
+    pub const fn jitter(&self) -> [f32; 2] {
+        self.0.jitter
+    }
+    /// The framebuffer the program draws to: the window, or a tile texture
+    /// when rendering offline. Draw to it from [`AppHandler::draw`] for the
+    /// drawing to end up in the rendered images.
+    #[must_use]
+    pub fn target(&self) -> gl::Framebuffer<sl::Vec4> {
+        self.0.target.clone()
+    }
     /// Create a uniform buffer binding for the app data.
     ///
     /// # Examples
@@ -0,0 +1,409 @@
+//! Offline rendering of images larger than the window, drawn in tiles and
+//! stitched together, optionally accumulating jittered sub-frames, and of
+//! animations as image sequences or videos.
+
+use std::{
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use image::{imageops, Rgba, RgbaImage};
+use posh::{
+    gl::{self, BufferUsage, DrawError, PrimitiveMode, Sampler2dSettings},
+    glow::{self, HasContext},
+    sl, Sl, UniformInterface, UniformInterfaceDom, VsInterface,
+};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::{
+    app::AppHandler,
+    capture,
+    error::ErrKind,
+    stage::{SettingsStage, UniformStage, VertexStage},
+    utils,
+    video::Encoder,
+    Handle, Program, RenderConfig,
+};
+
+/// The part of a larger image that is being drawn.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Tile {
+    pub(crate) image_size: PhysicalSize<u32>,
+    /// From the bottom left corner of the image.
+    pub(crate) offset: PhysicalPosition<u32>,
+}
+
+/// Floating point textures the sub-frames of a tile are drawn to and summed
+/// in on the GPU, so the sum keeps its precision and is read back once.
+pub(crate) struct Accumulator {
+    size: PhysicalSize<u32>,
+    /// The sum so far, the sub-frame being drawn and the texture the next
+    /// sum is drawn to.
+    textures: [gl::ColorTexture2d<sl::Vec4>; 3],
+    /// The OpenGL names of `textures`, in the same order.
+    names: [glow::Texture; 3],
+    /// The sum is attached to it to be read back, whatever is bound for
+    /// drawing at the time.
+    read_framebuffer: glow::Framebuffer,
+    raw_gl: Rc<glow::Context>,
+    program: gl::Program<Sum<Sl>, sl::Vec2>,
+    quad: gl::VertexBuffer<sl::Vec2>,
+}
+
+#[derive(Clone, UniformInterface)]
+struct Sum<D: UniformInterfaceDom> {
+    sum: D::ColorSampler2d<sl::Vec4>,
+    sample: D::ColorSampler2d<sl::Vec4>,
+}
+
+fn sum_shader(textures: Sum<Sl>, uv: sl::Vec2) -> sl::Vec4 {
+    textures.sum.sample(uv) + textures.sample.sample(uv)
+}
+
+impl Accumulator {
+    fn new(
+        gl: &gl::Context,
+        raw_gl: Rc<glow::Context>,
+        size: PhysicalSize<u32>,
+    ) -> Result<Self, ErrKind> {
+        let texture = || -> Result<_, ErrKind> {
+            let texture = gl.create_color_texture_2d(gl::ColorImage::rgba_f32_zero([
+                size.width,
+                size.height,
+            ]))?;
+            // posh binds a new texture to upload its image and doesn't expose
+            // its name otherwise
+            let name = unsafe { raw_gl.get_parameter_i32(glow::TEXTURE_BINDING_2D) };
+            let name = u32::try_from(name)
+                .ok()
+                .and_then(NonZeroU32::new)
+                .map(glow::NativeTexture)
+                .ok_or_else(|| ErrKind::ReadbackError("the tile texture has no name".into()))?;
+            Ok((texture, name))
+        };
+        let [(sum, sum_name), (sample, sample_name), (spare, spare_name)] =
+            [texture()?, texture()?, texture()?];
+        let read_framebuffer =
+            unsafe { raw_gl.create_framebuffer() }.map_err(ErrKind::ReadbackError)?;
+        Ok(Self {
+            size,
+            textures: [sum, sample, spare],
+            names: [sum_name, sample_name, spare_name],
+            read_framebuffer,
+            raw_gl,
+            program: gl.create_program(utils::fullscreen_vertex_shader, sum_shader)?,
+            quad: gl.create_vertex_buffer(&utils::full_screen_quad(), BufferUsage::StaticDraw)?,
+        })
+    }
+
+    /// Where sub-frame `index` is drawn, straight into the sum for the first.
+    fn target(&self, index: u32) -> gl::Framebuffer<sl::Vec4> {
+        let texture = &self.textures[usize::from(index > 0)];
+        texture.as_color_attachment().into()
+    }
+
+    /// Add the sub-frame drawn to the target to the sum.
+    fn add(&mut self) -> Result<(), DrawError> {
+        let sampler = |texture: &gl::ColorTexture2d<sl::Vec4>| {
+            texture.as_color_sampler(Sampler2dSettings::default())
+        };
+        self.program
+            .with_uniforms(Sum {
+                sum: sampler(&self.textures[0]),
+                sample: sampler(&self.textures[1]),
+            })
+            .with_framebuffer(gl::Framebuffer::from(
+                self.textures[2].as_color_attachment(),
+            ))
+            .with_settings(gl::DrawSettings::default())
+            .draw(self.quad.as_vertex_spec(PrimitiveMode::Triangles))?;
+        self.textures.swap(0, 2);
+        self.names.swap(0, 2);
+        Ok(())
+    }
+
+    /// Read back the sum, as rows of RGBA values with the first row at the
+    /// top.
+    fn read_sum(&self) -> Vec<f32> {
+        let gl = &self.raw_gl;
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.read_framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::READ_FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(self.names[0]),
+                0,
+            );
+            gl.read_buffer(glow::COLOR_ATTACHMENT0);
+        }
+        let sum = capture::read_pixels_f32(gl, self.size);
+        unsafe { gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None) };
+        sum
+    }
+}
+
+impl Drop for Accumulator {
+    fn drop(&mut self) {
+        unsafe { self.raw_gl.delete_framebuffer(self.read_framebuffer) };
+    }
+}
+
+impl<U, V, Vx, Un, St> Program<U, V, sl::Vec4, Vx, Un, St>
+where
+    U: UniformInterface<Sl> + 'static,
+    V: VsInterface<Sl> + 'static,
+    Vx: VertexStage<V>,
+    Un: UniformStage<U>,
+    St: SettingsStage,
+{
+    /// Render one image of `config.size`, which may be far larger than the
+    /// largest viewport, by drawing it in tiles of [`RenderConfig::tile_size`]
+    /// into a texture.
+    ///
+    /// While a tile is drawn, [`Handle::app`] reports the size of the whole
+    /// image along with the offset and size of the tile. Shaders have to
+    /// place themselves in the image with
+    /// [`image_clip_pos`](crate::utils::image_clip_pos) for the tiles to fit
+    /// together. Each tile is the average of [`RenderConfig::accumulate`]
+    /// sub-frames, jittered by [`Handle::jitter`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ErrKind::InvalidTileSize`] if a tile is
+    /// smaller than a supersampled pixel, and an error if drawing fails.
+    pub fn render_image(&mut self, config: &RenderConfig) -> Result<RgbaImage, ErrKind> {
+        let mut accumulator = self.accumulator(config)?;
+        self.render_tiles(&mut (), config, &mut accumulator, Duration::ZERO)
+    }
+
+    /// The textures the tiles of `config` are drawn to.
+    fn accumulator(&self, config: &RenderConfig) -> Result<Accumulator, ErrKind> {
+        let factor = config.supersampling.max(1);
+        // Whole blocks of samples, so that every tile downsamples on its own
+        let tile_size = PhysicalSize::new(
+            config.tile_size.width / factor * factor,
+            config.tile_size.height / factor * factor,
+        );
+        if tile_size.width == 0 || tile_size.height == 0 {
+            return Err(ErrKind::InvalidTileSize {
+                tile_size: config.tile_size,
+                supersampling: factor,
+            });
+        }
+        Accumulator::new(&self.state.gl, self.state.raw_gl.clone(), tile_size)
+    }
+
+    /// Update `handler` by `delta` and render the image of the current frame.
+    pub(crate) fn render_tiles(
+        &mut self,
+        handler: &mut impl AppHandler,
+        config: &RenderConfig,
+        accumulator: &mut Accumulator,
+        delta: Duration,
+    ) -> Result<RgbaImage, ErrKind> {
+        handler.update(Handle(&self.state), delta);
+        let window_size = std::mem::replace(&mut self.state.size, accumulator.size);
+        let image = self.stitch_tiles(handler, accumulator, config);
+        self.state.target = gl::Framebuffer::default();
+        self.state.size = window_size;
+        image
+    }
+
+    /// Draw every tile of the image and stitch them together downsampled.
+    fn stitch_tiles(
+        &mut self,
+        handler: &mut impl AppHandler,
+        accumulator: &mut Accumulator,
+        config: &RenderConfig,
+    ) -> Result<RgbaImage, ErrKind> {
+        let factor = config.supersampling.max(1);
+        let tile_size = self.state.size;
+        let image_size = PhysicalSize::new(
+            config.size.width.saturating_mul(factor),
+            config.size.height.saturating_mul(factor),
+        );
+        let mut image = RgbaImage::new(config.size.width, config.size.height);
+        let tiles = |image: u32, tile: u32| (0..image).step_by(tile as usize);
+        for y in tiles(image_size.height, tile_size.height) {
+            for x in tiles(image_size.width, tile_size.width) {
+                let tile = Tile {
+                    image_size,
+                    offset: PhysicalPosition::new(x, y),
+                };
+                let samples = config.accumulate.max(1);
+                let tile = self.render_tile(handler, accumulator, tile, samples)?;
+                // Tiles along the top and right edges stick out of the image
+                let width = tile_size.width.min(image_size.width - x);
+                let height = tile_size.height.min(image_size.height - y);
+                let visible =
+                    imageops::crop_imm(&tile, 0, tile_size.height - height, width, height);
+                let visible = downsample(visible.to_image(), factor);
+                let top = (image_size.height - y - height) / factor;
+                imageops::replace(&mut image, &visible, i64::from(x / factor), i64::from(top));
+            }
+        }
+        Ok(image)
+    }
+
+    /// Render the frames of `config`, at evenly spaced times, and write them
+    /// to its output.
+    pub(crate) fn render_output(
+        &mut self,
+        handler: &mut impl AppHandler,
+        config: &RenderConfig,
+    ) -> Result<(), ErrKind> {
+        if !config.framerate.is_finite() || config.framerate <= 0.0 {
+            return Err(ErrKind::InvalidRenderConfig(format!(
+                "framerate must be positive, got {}",
+                config.framerate
+            )));
+        }
+        let frames = match config.loop_duration {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Some(duration) => (duration.as_secs_f64() * f64::from(config.framerate)).round() as u32,
+            None => config.frames,
+        }
+        .max(1);
+        let mut encoder = config
+            .video
+            .as_ref()
+            .map(|video| Encoder::spawn(video, config.size, config.framerate, &config.output))
+            .transpose()?;
+        let mut accumulator = self.accumulator(config)?;
+        let frame_time = Duration::from_secs_f64(1.0 / f64::from(config.framerate));
+        for frame in 0..frames {
+            self.state.set_time(Duration::from_secs_f64(
+                f64::from(frame) / f64::from(config.framerate),
+            ));
+            self.state.loop_phase = config
+                .loop_duration
+                .map(|_| phase(f64::from(frame) / f64::from(frames)));
+            let delta = if frame == 0 {
+                Duration::ZERO
+            } else {
+                frame_time
+            };
+            let image = self.render_tiles(handler, config, &mut accumulator, delta)?;
+            if let Some(encoder) = encoder.as_mut() {
+                encoder.write_frame(&image)?;
+                info!("Encoded frame {}/{}", frame + 1, frames);
+            } else {
+                let path = if frames == 1 {
+                    config.output.clone()
+                } else {
+                    sequence_path(&config.output, frame, frames)
+                };
+                capture::save_image(&image, &path)?;
+                info!("Frame saved to {}", path.display());
+            }
+        }
+        encoder.map_or(Ok(()), Encoder::finish)
+    }
+
+    /// Draw `tile` as the average of `samples` jittered sub-frames.
+    fn render_tile(
+        &mut self,
+        handler: &mut impl AppHandler,
+        accumulator: &mut Accumulator,
+        tile: Tile,
+        samples: u32,
+    ) -> Result<RgbaImage, ErrKind> {
+        for sample in 0..samples {
+            self.state.tile = Some(tile);
+            self.state.target = accumulator.target(sample);
+            if samples > 1 {
+                // Spread evenly over the pixel by the (2, 3) Halton sequence
+                self.state.jitter = [halton(sample + 1, 2) - 0.5, halton(sample + 1, 3) - 0.5];
+            }
+            let drawn = self.render_frame();
+            handler.draw(Handle(&self.state));
+            self.state.tile = None;
+            self.state.jitter = [0.0; 2];
+            drawn?;
+            if sample > 0 {
+                accumulator.add()?;
+            }
+        }
+        let size = self.state.size;
+        let sum = accumulator.read_sum();
+        #[allow(clippy::cast_precision_loss)]
+        let scale = 255.0 / samples as f32;
+        let mut image = RgbaImage::new(size.width, size.height);
+        for (value, sum) in image.iter_mut().zip(sum) {
+            // Clamped to the range of a byte
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let average = (sum * scale).round().clamp(0.0, 255.0) as u8;
+            *value = average;
+        }
+        Ok(image)
+    }
+}
+
+/// Element `index` of the Halton sequence in `base`, within `[0, 1)`.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut scale = 1.0;
+    #[allow(clippy::cast_precision_loss)]
+    let inverse = 1.0 / base as f32;
+    while index > 0 {
+        scale *= inverse;
+        #[allow(clippy::cast_precision_loss)]
+        let digit = (index % base) as f32;
+        result += scale * digit;
+        index /= base;
+    }
+    result
+}
+
+/// Position of `time` in a loop of `duration`, `None` for an empty loop.
+pub(crate) fn loop_phase(time: Duration, duration: Duration) -> Option<f32> {
+    (!duration.is_zero()).then(|| phase((time.as_secs_f64() / duration.as_secs_f64()).fract()))
+}
+
+/// `fraction` in `[0, 1)` as `f32`, without rounding up to 1.
+fn phase(fraction: f64) -> f32 {
+    #[allow(clippy::cast_possible_truncation)]
+    let phase = fraction as f32;
+    if phase < 1.0 {
+        phase
+    } else {
+        1.0 - f32::EPSILON / 2.0
+    }
+}
+
+/// `output` with the frame number appended to its name, padded to the same
+/// width for every frame so the files sort in order.
+fn sequence_path(output: &Path, frame: u32, frames: u32) -> PathBuf {
+    let digits = (frames - 1).to_string().len().max(4);
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}-{frame:0digits$}");
+    if let Some(extension) = output.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    output.with_file_name(name)
+}
+
+/// Average every `factor`×`factor` block of pixels into one.
+fn downsample(image: RgbaImage, factor: u32) -> RgbaImage {
+    if factor <= 1 {
+        return image;
+    }
+    let samples = factor * factor;
+    RgbaImage::from_fn(image.width() / factor, image.height() / factor, |x, y| {
+        let mut sum = [0u32; 4];
+        for dy in 0..factor {
+            for dx in 0..factor {
+                let pixel = image.get_pixel(x * factor + dx, y * factor + dy);
+                for (sum, channel) in sum.iter_mut().zip(pixel.0) {
+                    *sum += u32::from(channel);
+                }
+            }
+        }
+        // Rounded to the nearest value, which always fits
+        Rgba(sum.map(|sum| u8::try_from((sum + samples / 2) / samples).unwrap_or(u8::MAX)))
+    })
+}
@@ -0,0 +1,369 @@
+use std::{ops::RangeInclusive, str::FromStr};
+
+use posh::gl;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ErrKind;
+
+/// The current value of a tweakable parameter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Float(f32),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Bool(bool),
+}
+
+/// How a parameter is presented to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamKind {
+    /// A slider limited to the given range.
+    Slider(RangeInclusive<f32>),
+    /// A color picker for `Vec3` and `Vec4` values.
+    Color,
+    /// A checkbox for `Bool` values.
+    Checkbox,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub value: ParamValue,
+    pub default: ParamValue,
+    pub kind: ParamKind,
+}
+
+impl Param {
+    #[must_use]
+    pub fn float(name: impl Into<String>, default: f32, range: RangeInclusive<f32>) -> Self {
+        Self::new(name, ParamValue::Float(default), ParamKind::Slider(range))
+    }
+
+    #[must_use]
+    pub fn color3(name: impl Into<String>, default: [f32; 3]) -> Self {
+        Self::new(name, ParamValue::Vec3(default), ParamKind::Color)
+    }
+
+    #[must_use]
+    pub fn color4(name: impl Into<String>, default: [f32; 4]) -> Self {
+        Self::new(name, ParamValue::Vec4(default), ParamKind::Color)
+    }
+
+    #[must_use]
+    pub fn toggle(name: impl Into<String>, default: bool) -> Self {
+        Self::new(name, ParamValue::Bool(default), ParamKind::Checkbox)
+    }
+
+    fn new(name: impl Into<String>, default: ParamValue, kind: ParamKind) -> Self {
+        Self {
+            name: name.into(),
+            value: default.clone(),
+            default,
+            kind,
+        }
+    }
+
+    /// Reset the value back to its default.
+    pub fn reset(&mut self) {
+        self.value = self.default.clone();
+    }
+}
+
+/// Types that can be read out of a [`ParamValue`].
+pub trait FromParam: Sized {
+    fn from_param(value: &ParamValue) -> Option<Self>;
+}
+
+impl FromParam for ParamValue {
+    fn from_param(value: &ParamValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromParam for f32 {
+    fn from_param(value: &ParamValue) -> Option<Self> {
+        match value {
+            ParamValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromParam for bool {
+    fn from_param(value: &ParamValue) -> Option<Self> {
+        match value {
+            ParamValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromParam for [f32; 3] {
+    fn from_param(value: &ParamValue) -> Option<Self> {
+        match value {
+            ParamValue::Vec3(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromParam for [f32; 4] {
+    fn from_param(value: &ParamValue) -> Option<Self> {
+        match value {
+            ParamValue::Vec4(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromParam for gl::Vec3 {
+    fn from_param(value: &ParamValue) -> Option<Self> {
+        <[f32; 3]>::from_param(value).map(|[x, y, z]| Self { x, y, z })
+    }
+}
+
+impl FromParam for gl::Vec4 {
+    fn from_param(value: &ParamValue) -> Option<Self> {
+        <[f32; 4]>::from_param(value).map(|[x, y, z, w]| Self { x, y, z, w })
+    }
+}
+
+/// Types that can be stored as a [`ParamValue`].
+pub trait ToParam {
+    fn to_param(self) -> ParamValue;
+}
+
+impl ToParam for ParamValue {
+    fn to_param(self) -> ParamValue {
+        self
+    }
+}
+
+impl ToParam for f32 {
+    fn to_param(self) -> ParamValue {
+        ParamValue::Float(self)
+    }
+}
+
+impl ToParam for bool {
+    fn to_param(self) -> ParamValue {
+        ParamValue::Bool(self)
+    }
+}
+
+impl ToParam for [f32; 3] {
+    fn to_param(self) -> ParamValue {
+        ParamValue::Vec3(self)
+    }
+}
+
+impl ToParam for [f32; 4] {
+    fn to_param(self) -> ParamValue {
+        ParamValue::Vec4(self)
+    }
+}
+
+impl ToParam for gl::Vec3 {
+    fn to_param(self) -> ParamValue {
+        ParamValue::Vec3([self.x, self.y, self.z])
+    }
+}
+
+impl ToParam for gl::Vec4 {
+    fn to_param(self) -> ParamValue {
+        ParamValue::Vec4([self.x, self.y, self.z, self.w])
+    }
+}
+
+impl FromStr for ParamValue {
+    type Err = ErrKind;
+
+    /// Parse `true`/`false`, a single float, or 3 or 4 comma separated floats.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = || ErrKind::InvalidParam(format!("Invalid parameter value `{input}`"));
+        match input.trim() {
+            "true" => return Ok(Self::Bool(true)),
+            "false" => return Ok(Self::Bool(false)),
+            _ => {}
+        }
+        let floats = input
+            .split(',')
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        match floats[..] {
+            [x] => Ok(Self::Float(x)),
+            [x, y, z] => Ok(Self::Vec3([x, y, z])),
+            [x, y, z, w] => Ok(Self::Vec4([x, y, z, w])),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A uniform block whose fields are exposed as tweakable parameters.
+///
+/// Usually implemented with `#[derive(Params)]` next to posh's
+/// `#[derive(Block)]`:
+///
+/// ```ignore
+/// #[derive(Clone, Copy, Block, Params)]
+/// #[repr(C)]
+/// struct Uniforms<D: BlockDom> {
+///     #[param(range = 0.0..=10.0, default = 1.0)]
+///     speed: D::F32,
+///     #[param(default = [1.0, 0.5, 0.0])]
+///     tint: D::Vec3,
+///     #[param(skip)]
+///     time: D::F32,
+/// }
+/// ```
+pub trait ParamBlock: Sized {
+    /// Descriptions of every parameter field, set to their defaults.
+    fn params() -> Vec<Param>;
+    /// Build the block from the current parameter values. Skipped or missing
+    /// fields use their defaults.
+    fn from_params(params: &Params) -> Self;
+    /// Write the fields of this block into `params`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a field's parameter isn't
+    /// registered in `params`, see [`Params::set`].
+    fn write_params(&self, params: &mut Params) -> Result<(), ErrKind>;
+}
+
+/// The parameters registered on a [`Program`](crate::Program), readable from
+/// callbacks through [`Handle::params`](crate::Handle::params).
+#[derive(Debug, Clone, Default)]
+pub struct Params {
+    params: Vec<Param>,
+}
+
+impl Params {
+    /// Register a parameter, replacing any existing parameter with the same name.
+    pub fn insert(&mut self, param: Param) {
+        match self.params.iter_mut().find(|p| p.name == param.name) {
+            Some(existing) => *existing = param,
+            None => self.params.push(param),
+        }
+    }
+
+    #[must_use]
+    pub fn get<T: FromParam>(&self, name: &str) -> Option<T> {
+        self.params
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| T::from_param(&p.value))
+    }
+
+    /// Set the value of an already registered parameter.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no parameter with the given name
+    /// exists or if the new value has a different type.
+    pub fn set(&mut self, name: &str, value: impl ToParam) -> Result<(), ErrKind> {
+        let value = value.to_param();
+        let param = self
+            .get_mut(name)
+            .ok_or_else(|| ErrKind::InvalidParam(format!("Unknown parameter `{name}`")))?;
+        if std::mem::discriminant(&param.value) != std::mem::discriminant(&value) {
+            return Err(ErrKind::InvalidParam(format!(
+                "Type mismatch for parameter `{name}`: expected {:?}, got {value:?}",
+                param.value
+            )));
+        }
+        param.value = value;
+        Ok(())
+    }
+
+    /// Apply a `name=value` assignment, as passed on the command line.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the assignment is malformed or
+    /// [`Params::set`] fails.
+    pub fn apply_assignment(&mut self, assignment: &str) -> Result<(), ErrKind> {
+        let (name, value) = assignment.split_once('=').ok_or_else(|| {
+            ErrKind::InvalidParam(format!("Expected `name=value`, got `{assignment}`"))
+        })?;
+        self.set(name.trim(), value.parse::<ParamValue>()?)
+    }
+
+    /// Copy the values of the parameters in `other` that are registered here
+    /// with the same name and type.
+    pub(crate) fn copy_values(&mut self, other: &Self) {
+        for param in &mut self.params {
+            let value = other
+                .iter()
+                .find(|p| p.name == param.name)
+                .map(|p| &p.value)
+                .filter(|v| std::mem::discriminant(*v) == std::mem::discriminant(&param.value));
+            if let Some(value) = value {
+                param.value.clone_from(value);
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Param> {
+        self.params.iter_mut().find(|p| p.name == name)
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Param> {
+        self.params.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Param> {
+        self.params.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Option<ParamValue> {
+        input.parse().ok()
+    }
+
+    #[test]
+    fn parses_every_value_type() {
+        assert_eq!(parse("true"), Some(ParamValue::Bool(true)));
+        assert_eq!(parse(" false "), Some(ParamValue::Bool(false)));
+        assert_eq!(parse("1.5"), Some(ParamValue::Float(1.5)));
+        assert_eq!(parse("-2"), Some(ParamValue::Float(-2.0)));
+        assert_eq!(parse("1, 0.5,0"), Some(ParamValue::Vec3([1.0, 0.5, 0.0])));
+        assert_eq!(
+            parse("0.1,0.2,0.3,1"),
+            Some(ParamValue::Vec4([0.1, 0.2, 0.3, 1.0]))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        for input in ["", "yes", "1,2", "1,2,3,4,5", "1,,2", "1;2;3", "True"] {
+            assert_eq!(parse(input), None, "`{input}` should not parse");
+        }
+    }
+
+    #[test]
+    fn assignments_keep_the_parameter_type() {
+        let mut params = Params::default();
+        params.insert(Param::float("speed", 1.0, 0.0..=10.0));
+        params.insert(Param::toggle("glow", false));
+        assert!(params.apply_assignment("speed = 2.5").is_ok());
+        assert!(params.apply_assignment("glow=true").is_ok());
+        assert_eq!(params.get::<f32>("speed"), Some(2.5));
+        assert_eq!(params.get::<bool>("glow"), Some(true));
+        assert!(params.apply_assignment("speed=true").is_err());
+        assert!(params.apply_assignment("missing=1").is_err());
+        assert!(params.apply_assignment("speed").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Live playback of the audio track through the system output, driving the
+//! frame clock of windowed programs.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use rodio::{OutputStream, Sink, Source};
+
+use crate::audio::AudioTrack;
+
+/// The audio track playing alongside a window, started with
+/// [`WindowConfig::play_audio`](crate::WindowConfig::play_audio). While it
+/// plays, [`Handle::time`](crate::Handle::time) follows the playback position,
+/// so pausing and seeking move the visuals along with the sound.
+pub struct Playback {
+    /// Playback stops when the stream is dropped.
+    _stream: OutputStream,
+    sink: Sink,
+    samples: Arc<[f32]>,
+    sample_rate: u32,
+    /// Index of the next sample handed to the output.
+    position: Arc<AtomicUsize>,
+    /// The latest pull of the output.
+    pull: Arc<Mutex<Option<Pull>>>,
+}
+
+/// Samples further apart than this were pulled by separate callbacks of the
+/// output. Callbacks come at least a few milliseconds apart, while a callback
+/// pulls its samples within microseconds.
+const PULL_GAP: Duration = Duration::from_millis(1);
+
+/// A batch of samples pulled by the output, to interpolate the position
+/// between pulls.
+#[derive(Debug, Clone, Copy)]
+struct Pull {
+    /// Index of the first sample.
+    index: usize,
+    at: Instant,
+    /// Samples of the previous pull, which the output plays before these.
+    buffered: usize,
+}
+
+impl Playback {
+    /// Start playing `track` from its beginning, or `None`, after a warning,
+    /// if there is no audio output.
+    pub(crate) fn start(track: &AudioTrack) -> Option<Self> {
+        let (stream, output) = OutputStream::try_default()
+            .map_err(|err| warn!("No audio output, the audio track is not played: {}", err))
+            .ok()?;
+        let sink = Sink::try_new(&output)
+            .map_err(|err| warn!("Failed to play the audio track: {}", err))
+            .ok()?;
+        let playback = Self {
+            _stream: stream,
+            sink,
+            samples: track.samples(),
+            sample_rate: track.sample_rate(),
+            position: Arc::new(AtomicUsize::new(0)),
+            pull: Arc::new(Mutex::new(None)),
+        };
+        playback.append();
+        Some(playback)
+    }
+
+    /// Queue the samples from the current position.
+    fn append(&self) {
+        self.sink.append(TrackSource {
+            samples: self.samples.clone(),
+            sample_rate: self.sample_rate,
+            position: self.position.clone(),
+            pull: self.pull.clone(),
+            last_sample: None,
+            pulled: 0,
+        });
+    }
+
+    /// The audible playback position. The output hands over samples in
+    /// batches, the position moves on smoothly in between. rodio doesn't
+    /// report the latency of the output, it is taken to be one batch, which
+    /// the output plays before the latest one.
+    #[must_use]
+    pub fn position(&self) -> Duration {
+        let rate = f64::from(self.sample_rate.max(1));
+        let handed_over = self.position.load(Ordering::Relaxed);
+        let pull = self.pull.lock().ok().and_then(|pull| *pull);
+        // Sample indices are far from the precision limit of `f64`
+        #[allow(clippy::cast_precision_loss)]
+        let samples = match pull {
+            Some(pull) => {
+                let audible = (pull.index as f64 - pull.buffered as f64)
+                    + pull.at.elapsed().as_secs_f64() * rate;
+                // Stops at the end of the pull while paused or starved
+                audible.clamp(0.0, handed_over.saturating_sub(pull.buffered) as f64)
+            }
+            None => handed_over as f64,
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let samples = samples.min(self.samples.len() as f64);
+        Duration::from_secs_f64(samples / rate)
+    }
+
+    /// Continue playing from `position`, also after the track has ended.
+    pub fn seek(&self, position: Duration) {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let index = (position.as_secs_f64() * f64::from(self.sample_rate)) as usize;
+        self.position
+            .store(index.min(self.samples.len()), Ordering::Relaxed);
+        if let Ok(mut pull) = self.pull.lock() {
+            *pull = None;
+        }
+        if self.sink.empty() {
+            self.append();
+        }
+    }
+
+    pub fn pause(&self) {
+        self.sink.pause();
+    }
+
+    pub fn play(&self) {
+        self.sink.play();
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    /// Whether the whole track has been played.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.sink.empty()
+    }
+}
+
+/// The samples of a track from a position shared with [`Playback`], so the
+/// position can be read and moved while playing.
+struct TrackSource {
+    samples: Arc<[f32]>,
+    sample_rate: u32,
+    position: Arc<AtomicUsize>,
+    pull: Arc<Mutex<Option<Pull>>>,
+    last_sample: Option<Instant>,
+    /// Samples of the current pull so far.
+    pulled: usize,
+}
+
+impl Iterator for TrackSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let index = self.position.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        if self
+            .last_sample
+            .is_none_or(|last| now.duration_since(last) > PULL_GAP)
+        {
+            if let Ok(mut pull) = self.pull.lock() {
+                *pull = Some(Pull {
+                    index,
+                    at: now,
+                    buffered: self.pulled,
+                });
+            }
+            self.pulled = 0;
+        }
+        self.last_sample = Some(now);
+        self.pulled += 1;
+        self.samples.get(index).copied()
+    }
+}
+
+impl Source for TrackSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
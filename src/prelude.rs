@@ -1,4 +1,5 @@
 pub use crate::error::ErrKind;
+pub use crate::params::{Param, ParamBlock, Params};
 pub use crate::Program;
 pub use crate::*;
 pub use gl::Context;
@@ -0,0 +1,103 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ErrKind, PresetFileError},
+    params::{ParamValue, Params},
+};
+
+/// Named parameter values that can be shared as a TOML or JSON file.
+///
+/// The format is picked from the file extension: `.json` files are read and
+/// written as JSON, anything else as TOML.
+///
+/// ```toml
+/// [values]
+/// speed = 2.5
+/// tint = [1.0, 0.5, 0.0]
+/// enabled = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub values: BTreeMap<String, ParamValue>,
+}
+
+impl Preset {
+    /// Capture the current values of `params`.
+    #[must_use]
+    pub fn from_params(params: &Params) -> Self {
+        Self {
+            values: params
+                .iter()
+                .map(|param| (param.name.clone(), param.value.clone()))
+                .collect(),
+        }
+    }
+
+    /// Write the preset values into `params`. Values of parameters that
+    /// aren't registered, e.g. ones removed since the preset was saved, are
+    /// skipped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a value has a different type than
+    /// its registered parameter, see [`Params::set`].
+    pub fn apply(&self, params: &mut Params) -> Result<(), ErrKind> {
+        for (name, value) in &self.values {
+            if params.get_mut(name).is_none() {
+                warn!("Skipping unknown parameter `{}` in the preset", name);
+                continue;
+            }
+            params.set(name, value.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Load a preset from a TOML or JSON file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be read or parsed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ErrKind> {
+        let path = path.as_ref();
+        let load = || -> Result<Self, PresetFileError> {
+            let contents = fs::read_to_string(path)?;
+            if is_json(path) {
+                Ok(serde_json::from_str(&contents)?)
+            } else {
+                Ok(toml::from_str(&contents)?)
+            }
+        };
+        load().map_err(|source| ErrKind::PresetError {
+            path: path.into(),
+            source,
+        })
+    }
+
+    /// Save the preset to a TOML or JSON file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the preset can't be serialized or
+    /// the file can't be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ErrKind> {
+        let path = path.as_ref();
+        let save = || -> Result<(), PresetFileError> {
+            let contents = if is_json(path) {
+                serde_json::to_string_pretty(self)?
+            } else {
+                toml::to_string_pretty(self)?
+            };
+            Ok(fs::write(path, contents)?)
+        };
+        save().map_err(|source| ErrKind::PresetError {
+            path: path.into(),
+            source,
+        })
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
@@ -0,0 +1,324 @@
+use std::{
+    collections::VecDeque,
+    ffi::{c_void, CStr},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use posh::glow::{self, HasContext};
+use serde::Serialize;
+
+use crate::{
+    error::{log_error, ErrKind, ProfileFileError},
+    stats::supports_timer_queries,
+};
+
+/// Scopes kept for [`Handle::gpu_scopes`](crate::Handle::gpu_scopes) and the
+/// trace. Older ones are dropped.
+pub const MAX_GPU_SCOPES: usize = 1 << 16;
+
+/// A finished GPU timing, with times relative to the start of profiling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuScope {
+    pub name: &'static str,
+    /// Frame the scope was recorded in.
+    pub frame: u64,
+    pub cpu_start: Duration,
+    pub cpu_duration: Duration,
+    pub gpu_start: Duration,
+    pub gpu_duration: Duration,
+}
+
+/// `glGetQueryObjectui64v`, which glow doesn't expose. Timestamps don't fit
+/// into the 32 bit results glow reads, drivers clamp them.
+#[derive(Clone, Copy)]
+pub(crate) struct QueryResultFn(unsafe extern "system" fn(u32, u32, *mut u64));
+
+impl QueryResultFn {
+    /// Load the function, or its GLES extension, with the loader of the
+    /// context. `None` if the driver has neither.
+    pub(crate) fn load(mut get_proc_address: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+        [c"glGetQueryObjectui64v", c"glGetQueryObjectui64vEXT"]
+            .into_iter()
+            .map(|name| get_proc_address(name))
+            .find(|address| !address.is_null())
+            .map(|address| {
+                // The function of that name has this signature
+                Self(unsafe {
+                    std::mem::transmute::<
+                        *const c_void,
+                        unsafe extern "system" fn(u32, u32, *mut u64),
+                    >(address)
+                })
+            })
+    }
+}
+
+struct PendingScope {
+    name: &'static str,
+    frame: u64,
+    cpu_start: Duration,
+    cpu_duration: Option<Duration>,
+    begin: glow::Query,
+    end: Option<glow::Query>,
+}
+
+/// Times named scopes on the GPU with `GL_TIMESTAMP` queries.
+///
+/// Timestamps, unlike `GL_TIME_ELAPSED` queries, can be nested, so a pass can
+/// be timed together with each of the draws inside it. Results are collected
+/// once the driver reports them as available and never stall the pipeline.
+///
+/// GPU times are aligned to the CPU clock at the first finished scope, so the
+/// two timelines in the trace are only approximately in sync.
+pub(crate) struct GpuProfiler {
+    gl: Rc<glow::Context>,
+    query_result: QueryResultFn,
+    trace_path: PathBuf,
+    epoch: Instant,
+    gpu_epoch: Option<(u64, Duration)>,
+    frame: u64,
+    open: Vec<usize>,
+    pending: VecDeque<PendingScope>,
+    free: Vec<glow::Query>,
+    scopes: Vec<GpuScope>,
+}
+
+impl GpuProfiler {
+    /// Returns `None` if the context doesn't support timer queries.
+    pub(crate) fn new(
+        gl: Rc<glow::Context>,
+        query_result: QueryResultFn,
+        trace_path: PathBuf,
+    ) -> Option<Self> {
+        supports_timer_queries(&gl).then(|| Self {
+            gl,
+            query_result,
+            trace_path,
+            epoch: Instant::now(),
+            gpu_epoch: None,
+            frame: 0,
+            open: Vec::new(),
+            pending: VecDeque::new(),
+            free: Vec::new(),
+            scopes: Vec::new(),
+        })
+    }
+
+    pub(crate) fn begin(&mut self, name: &'static str) {
+        let Some(begin) = self.timestamp() else {
+            return;
+        };
+        self.open.push(self.pending.len());
+        self.pending.push_back(PendingScope {
+            name,
+            frame: self.frame,
+            cpu_start: self.epoch.elapsed(),
+            cpu_duration: None,
+            begin,
+            end: None,
+        });
+    }
+
+    /// End the innermost open scope.
+    pub(crate) fn end(&mut self) {
+        let Some(index) = self.open.pop() else {
+            return;
+        };
+        let end = self.timestamp();
+        let cpu_end = self.epoch.elapsed();
+        let scope = &mut self.pending[index];
+        scope.cpu_duration = Some(cpu_end.saturating_sub(scope.cpu_start));
+        scope.end = end;
+    }
+
+    /// Collect every finished scope and start the next frame.
+    pub(crate) fn end_frame(&mut self) {
+        self.frame += 1;
+        if !self.open.is_empty() {
+            return;
+        }
+        while let Some(scope) = self.pending.pop_front() {
+            let Some(end) = scope.end else {
+                // Creating the end query failed, drop the scope
+                self.free.push(scope.begin);
+                continue;
+            };
+            let available = unsafe {
+                self.gl
+                    .get_query_parameter_u32(end, glow::QUERY_RESULT_AVAILABLE)
+            };
+            if available == 0 {
+                self.pending.push_front(scope);
+                break;
+            }
+            let begin = self.query_result(scope.begin);
+            let end_time = self.query_result(end);
+            self.free.extend([scope.begin, end]);
+            self.finish(&scope, begin, end_time);
+        }
+    }
+
+    fn finish(&mut self, scope: &PendingScope, begin: u64, end: u64) {
+        let &mut (gpu_epoch, cpu_offset) = self.gpu_epoch.get_or_insert((begin, scope.cpu_start));
+        let scope = GpuScope {
+            name: scope.name,
+            frame: scope.frame,
+            cpu_start: scope.cpu_start,
+            cpu_duration: scope.cpu_duration.unwrap_or_default(),
+            gpu_start: cpu_offset + Duration::from_nanos(begin.saturating_sub(gpu_epoch)),
+            gpu_duration: Duration::from_nanos(end.saturating_sub(begin)),
+        };
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            name = scope.name,
+            frame = scope.frame,
+            gpu_ms = scope.gpu_duration.as_secs_f64() * 1000.0,
+            cpu_ms = scope.cpu_duration.as_secs_f64() * 1000.0,
+            "gpu_scope"
+        );
+        if self.scopes.len() == MAX_GPU_SCOPES {
+            // Dropped in chunks, so the scopes stay one slice without moving
+            // them for every new one
+            self.scopes.drain(..MAX_GPU_SCOPES / 4);
+        }
+        self.scopes.push(scope);
+    }
+
+    fn timestamp(&mut self) -> Option<glow::Query> {
+        let query = match self.free.pop() {
+            Some(query) => query,
+            None => match unsafe { self.gl.create_query() } {
+                Ok(query) => query,
+                Err(err) => {
+                    warn!("Failed to create a timer query: {}", err);
+                    return None;
+                }
+            },
+        };
+        unsafe { self.gl.query_counter(query, glow::TIMESTAMP) };
+        Some(query)
+    }
+
+    fn query_result(&self, query: glow::Query) -> u64 {
+        let mut result = 0;
+        unsafe { (self.query_result.0)(query.0.get(), glow::QUERY_RESULT, &mut result) };
+        result
+    }
+
+    /// The most recent [`MAX_GPU_SCOPES`] finished scopes.
+    pub(crate) fn scopes(&self) -> &[GpuScope] {
+        &self.scopes
+    }
+
+    /// Write the finished scopes to the trace file.
+    pub(crate) fn save(&self) -> Result<(), ErrKind> {
+        save_chrome_trace(&self.scopes, &self.trace_path)?;
+        info!("GPU trace saved to {}", self.trace_path.display());
+        Ok(())
+    }
+}
+
+impl Drop for GpuProfiler {
+    /// Saves the trace, however the program ends.
+    fn drop(&mut self) {
+        log_error(self.save());
+        for scope in self.pending.drain(..) {
+            unsafe { self.gl.delete_query(scope.begin) };
+            if let Some(end) = scope.end {
+                unsafe { self.gl.delete_query(end) };
+            }
+        }
+        for query in self.free.drain(..) {
+            unsafe { self.gl.delete_query(query) };
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromeTrace<'a> {
+    trace_events: Vec<TraceEvent<'a>>,
+    display_time_unit: &'static str,
+}
+
+/// A complete (`"ph": "X"`) event of the Chrome trace event format.
+#[derive(Serialize)]
+struct TraceEvent<'a> {
+    name: &'a str,
+    cat: &'static str,
+    ph: &'static str,
+    /// Start, in microseconds.
+    ts: f64,
+    /// Duration, in microseconds.
+    dur: f64,
+    pid: u32,
+    tid: u32,
+    args: TraceArgs,
+}
+
+#[derive(Serialize)]
+struct TraceArgs {
+    frame: u64,
+}
+
+const CPU_THREAD: u32 = 1;
+const GPU_THREAD: u32 = 2;
+
+/// Write `scopes` as a Chrome trace event JSON file, viewable in Perfetto or
+/// `chrome://tracing`. CPU and GPU timings are shown as separate threads.
+///
+/// # Errors
+///
+/// This function will return an error if the file can't be written.
+pub fn save_chrome_trace(scopes: &[GpuScope], path: impl AsRef<Path>) -> Result<(), ErrKind> {
+    let path = path.as_ref();
+    let micros = |time: Duration| time.as_secs_f64() * 1_000_000.0;
+    let event = |scope: &GpuScope, cat, tid, start, duration| TraceEvent {
+        name: scope.name,
+        cat,
+        ph: "X",
+        ts: micros(start),
+        dur: micros(duration),
+        pid: 1,
+        tid,
+        args: TraceArgs { frame: scope.frame },
+    };
+    let trace = ChromeTrace {
+        trace_events: scopes
+            .iter()
+            .flat_map(|scope| {
+                [
+                    event(
+                        scope,
+                        "cpu",
+                        CPU_THREAD,
+                        scope.cpu_start,
+                        scope.cpu_duration,
+                    ),
+                    event(
+                        scope,
+                        "gpu",
+                        GPU_THREAD,
+                        scope.gpu_start,
+                        scope.gpu_duration,
+                    ),
+                ]
+            })
+            .collect(),
+        display_time_unit: "ms",
+    };
+    let save = || -> Result<(), ProfileFileError> {
+        let file = fs::File::create(path)?;
+        Ok(serde_json::to_writer(
+            std::io::BufWriter::new(file),
+            &trace,
+        )?)
+    };
+    save().map_err(|source| ErrKind::ProfileError {
+        path: path.into(),
+        source,
+    })
+}
@@ -0,0 +1,113 @@
+//! The stages a [`Program`](crate::Program) needs before it can be served.
+//!
+//! Each stage is a generic parameter of `Program` that starts out as
+//! [`Missing`] and becomes [`Provided`] once its builder method is called, in
+//! any order. [`Program::serve`](crate::Program::serve) requires every stage
+//! to be usable, so forgetting one is a compile error naming the method to
+//! call.
+
+use posh::{
+    gl::{self, DrawError, VertexSpec},
+    sl, Sl, UniformInterface, VsInterface,
+};
+
+use crate::{Handle, SettingsFn, UniformsFn, VertexFn};
+
+/// A stage that hasn't been provided yet.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Missing;
+
+/// A stage provided with a builder method.
+pub struct Provided<C>(pub(crate) C);
+
+pub type VertexCallback<V> = Box<dyn VertexFn<V>>;
+pub type UniformsCallback<U> = Box<dyn UniformsFn<U>>;
+pub type SettingsCallback = Box<dyn SettingsFn>;
+
+#[diagnostic::on_unimplemented(
+    message = "the program has no vertices to draw",
+    label = "vertices missing",
+    note = "provide them with `Program::with_vertices` before serving"
+)]
+pub trait VertexStage<V: VsInterface<Sl>> {
+    fn vertex_spec(&self, handle: Handle) -> VertexSpec<V>;
+}
+
+impl<V: VsInterface<Sl>> VertexStage<V> for Provided<VertexCallback<V>> {
+    fn vertex_spec(&self, handle: Handle) -> VertexSpec<V> {
+        (self.0)(handle)
+    }
+}
+
+/// Uniforms are only optional for shaders that don't take any, i.e. when
+/// `U = ()`.
+#[diagnostic::on_unimplemented(
+    message = "the program's shaders take uniforms, but none are provided",
+    label = "uniforms missing",
+    note = "provide them with `Program::with_uniforms` before serving"
+)]
+pub trait UniformStage<U: UniformInterface<Sl>> {
+    /// Draw `program` into `framebuffer` with the uniforms of this stage.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if drawing fails.
+    fn draw<V: VsInterface<Sl>>(
+        &self,
+        program: &gl::Program<U, V, sl::Vec4>,
+        handle: Handle,
+        framebuffer: gl::Framebuffer<sl::Vec4>,
+        settings: gl::DrawSettings,
+        vertices: VertexSpec<V>,
+    ) -> Result<(), DrawError>;
+}
+
+impl<U: UniformInterface<Sl>> UniformStage<U> for Provided<UniformsCallback<U>> {
+    fn draw<V: VsInterface<Sl>>(
+        &self,
+        program: &gl::Program<U, V, sl::Vec4>,
+        handle: Handle,
+        framebuffer: gl::Framebuffer<sl::Vec4>,
+        settings: gl::DrawSettings,
+        vertices: VertexSpec<V>,
+    ) -> Result<(), DrawError> {
+        program
+            .with_settings(settings)
+            .with_uniforms((self.0)(handle))
+            .with_framebuffer(framebuffer)
+            .draw(vertices)?;
+        Ok(())
+    }
+}
+
+impl UniformStage<()> for Missing {
+    fn draw<V: VsInterface<Sl>>(
+        &self,
+        program: &gl::Program<(), V, sl::Vec4>,
+        _handle: Handle,
+        framebuffer: gl::Framebuffer<sl::Vec4>,
+        settings: gl::DrawSettings,
+        vertices: VertexSpec<V>,
+    ) -> Result<(), DrawError> {
+        program
+            .with_settings(settings)
+            .with_framebuffer(framebuffer)
+            .draw(vertices)?;
+        Ok(())
+    }
+}
+
+#[diagnostic::on_unimplemented(
+    message = "the program has no draw settings",
+    label = "draw settings missing",
+    note = "provide them with `Program::with_draw_settings` before serving"
+)]
+pub trait SettingsStage {
+    fn settings(&self, handle: Handle) -> gl::DrawSettings;
+}
+
+impl SettingsStage for Provided<SettingsCallback> {
+    fn settings(&self, handle: Handle) -> gl::DrawSettings {
+        (self.0)(handle)
+    }
+}
@@ -0,0 +1,279 @@
+use std::{collections::VecDeque, rc::Rc, time::Duration};
+
+use posh::glow::{self, HasContext};
+
+/// Number of frames kept by [`FrameStats`] by default, about two seconds at
+/// 60 FPS.
+pub const DEFAULT_WINDOW: usize = 120;
+
+/// Rolling frame timing statistics over the last few frames.
+///
+/// Three durations are tracked per frame:
+/// - the frame time, from the moment drawing of one frame starts to the
+///   moment drawing of the next starts. This includes handling the events and
+///   any sleep to hold the framerate in between. The first frame has no
+///   predecessor and reports its CPU time instead.
+/// - the CPU time from the start of drawing to the buffer swap, which leaves
+///   out event handling
+/// - the GPU time spent executing the draw, measured with `GL_TIME_ELAPSED`
+///   queries. GPU times arrive a few frames late, since results are only read
+///   once available, and are missing if the context has no timer queries.
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    window: usize,
+    frames: u64,
+    frame_times: VecDeque<Duration>,
+    cpu_times: VecDeque<Duration>,
+    gpu_times: VecDeque<Duration>,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
+    }
+}
+
+impl FrameStats {
+    /// Keep statistics over the last `window` frames.
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            frames: 0,
+            frame_times: VecDeque::with_capacity(window),
+            cpu_times: VecDeque::with_capacity(window),
+            gpu_times: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Total number of frames recorded, including the ones that have left the
+    /// window.
+    #[must_use]
+    pub const fn frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    /// The most recent frame time.
+    #[must_use]
+    pub fn last(&self) -> Option<Duration> {
+        self.frame_times.back().copied()
+    }
+
+    #[must_use]
+    pub fn average(&self) -> Duration {
+        average(&self.frame_times)
+    }
+
+    #[must_use]
+    pub fn min(&self) -> Duration {
+        self.frame_times.iter().min().copied().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn max(&self) -> Duration {
+        self.frame_times.iter().max().copied().unwrap_or_default()
+    }
+
+    /// The frame time that `percent` of the frames in the window are at or
+    /// below, e.g. `percentile(99)` for the 1% slowest frames. Values above
+    /// 100 are treated as 100.
+    #[must_use]
+    pub fn percentile(&self, percent: u8) -> Duration {
+        let mut sorted: Vec<_> = self.frame_times.iter().copied().collect();
+        if sorted.is_empty() {
+            return Duration::ZERO;
+        }
+        sorted.sort_unstable();
+        let index = (sorted.len() - 1) * usize::from(percent.min(100)) / 100;
+        sorted[index]
+    }
+
+    /// Frames per second, from the average frame time.
+    #[must_use]
+    pub fn fps(&self) -> f64 {
+        let average = self.average();
+        if average.is_zero() {
+            0.0
+        } else {
+            1.0 / average.as_secs_f64()
+        }
+    }
+
+    /// Average CPU time spent drawing and presenting a frame.
+    #[must_use]
+    pub fn cpu_time(&self) -> Duration {
+        average(&self.cpu_times)
+    }
+
+    /// Average GPU time spent drawing a frame, or `None` if no GPU timings
+    /// are available.
+    #[must_use]
+    pub fn gpu_time(&self) -> Option<Duration> {
+        (!self.gpu_times.is_empty()).then(|| average(&self.gpu_times))
+    }
+
+    pub(crate) fn record(&mut self, frame_time: Duration, cpu_time: Duration) {
+        self.frames += 1;
+        push(&mut self.frame_times, frame_time, self.window);
+        push(&mut self.cpu_times, cpu_time, self.window);
+    }
+
+    pub(crate) fn record_gpu(&mut self, gpu_time: Duration) {
+        push(&mut self.gpu_times, gpu_time, self.window);
+    }
+}
+
+fn push(times: &mut VecDeque<Duration>, time: Duration, window: usize) {
+    if times.len() == window {
+        times.pop_front();
+    }
+    times.push_back(time);
+}
+
+fn average(times: &VecDeque<Duration>) -> Duration {
+    if times.is_empty() {
+        return Duration::ZERO;
+    }
+    times.iter().sum::<Duration>() / u32::try_from(times.len()).unwrap_or(u32::MAX)
+}
+
+/// Measures GPU time with a ring of `GL_TIME_ELAPSED` queries.
+///
+/// Results are only read once the driver reports them as available, so
+/// timing never stalls the pipeline.
+pub(crate) struct GpuTimer {
+    gl: Rc<glow::Context>,
+    pending: VecDeque<glow::Query>,
+    free: Vec<glow::Query>,
+    active: bool,
+}
+
+impl GpuTimer {
+    /// Queries still in flight before new measurements are skipped.
+    const MAX_PENDING: usize = 4;
+
+    /// Returns `None` if the context doesn't support timer queries.
+    pub(crate) fn new(gl: Rc<glow::Context>) -> Option<Self> {
+        supports_timer_queries(&gl).then(|| Self {
+            gl,
+            pending: VecDeque::with_capacity(Self::MAX_PENDING),
+            free: Vec::with_capacity(Self::MAX_PENDING),
+            active: false,
+        })
+    }
+
+    pub(crate) fn begin(&mut self) {
+        if self.pending.len() >= Self::MAX_PENDING {
+            return;
+        }
+        let query = match self.free.pop() {
+            Some(query) => query,
+            None => match unsafe { self.gl.create_query() } {
+                Ok(query) => query,
+                Err(err) => {
+                    warn!("Failed to create a timer query: {}", err);
+                    return;
+                }
+            },
+        };
+        unsafe { self.gl.begin_query(glow::TIME_ELAPSED, query) };
+        self.pending.push_back(query);
+        self.active = true;
+    }
+
+    pub(crate) fn end(&mut self) {
+        if std::mem::take(&mut self.active) {
+            unsafe { self.gl.end_query(glow::TIME_ELAPSED) };
+        }
+    }
+
+    /// Collect the results of every finished query, oldest first.
+    pub(crate) fn poll(&mut self) -> Vec<Duration> {
+        let mut times = Vec::new();
+        // The newest query is still open between `begin` and `end`
+        let open = usize::from(self.active);
+        while self.pending.len() > open {
+            let Some(&query) = self.pending.front() else {
+                break;
+            };
+            let available = unsafe {
+                self.gl
+                    .get_query_parameter_u32(query, glow::QUERY_RESULT_AVAILABLE)
+            };
+            if available == 0 {
+                break;
+            }
+            // Nanoseconds, a 32 bit result covers frames of up to four seconds
+            let nanos = unsafe { self.gl.get_query_parameter_u32(query, glow::QUERY_RESULT) };
+            times.push(Duration::from_nanos(u64::from(nanos)));
+            self.pending.pop_front();
+            self.free.push(query);
+        }
+        times
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        self.end();
+        for query in self.pending.drain(..).chain(self.free.drain(..)) {
+            unsafe { self.gl.delete_query(query) };
+        }
+    }
+}
+
+pub(crate) fn supports_timer_queries(gl: &glow::Context) -> bool {
+    let version = gl.version();
+    let extensions = gl.supported_extensions();
+    (!version.is_embedded && (version.major, version.minor) >= (3, 3))
+        || extensions.contains("GL_ARB_timer_query")
+        || extensions.contains("GL_EXT_disjoint_timer_query")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn window_keeps_the_most_recent_frames() {
+        let mut stats = FrameStats::new(3);
+        for time in [10, 20, 30, 40, 50] {
+            stats.record(ms(time), ms(1));
+            stats.record_gpu(ms(time / 10));
+        }
+        assert_eq!(stats.frame_count(), 5);
+        assert_eq!(stats.last(), Some(ms(50)));
+        assert_eq!(stats.min(), ms(30));
+        assert_eq!(stats.max(), ms(50));
+        assert_eq!(stats.average(), ms(40));
+        assert_eq!(stats.gpu_time(), Some(ms(4)));
+    }
+
+    #[test]
+    fn empty_window() {
+        let stats = FrameStats::new(0);
+        assert_eq!(stats.last(), None);
+        assert_eq!(stats.average(), Duration::ZERO);
+        assert_eq!(stats.percentile(50), Duration::ZERO);
+        assert!(stats.fps().abs() < f64::EPSILON);
+        assert_eq!(stats.gpu_time(), None);
+    }
+
+    #[test]
+    fn percentiles_pick_the_sorted_frame() {
+        let mut stats = FrameStats::new(DEFAULT_WINDOW);
+        for time in (1..=100).rev() {
+            stats.record(ms(time), ms(1));
+        }
+        assert_eq!(stats.percentile(0), ms(1));
+        assert_eq!(stats.percentile(50), ms(50));
+        assert_eq!(stats.percentile(99), ms(99));
+        assert_eq!(stats.percentile(100), ms(100));
+        assert_eq!(stats.percentile(255), ms(100));
+    }
+}
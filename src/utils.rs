@@ -5,15 +5,49 @@ use posh::{
     Block, BlockDom, Sl, UniformInterface, UniformInterfaceDom,
 };
 
+pub mod color;
+pub mod noise;
+pub mod raymarch;
+pub mod sdf;
+pub mod transform;
+
 #[derive(Clone, UniformInterface)]
 pub struct Inner<D: UniformInterfaceDom> {
     pub res: D::ColorSampler2d<sl::Vec2>,
 }
 
+/// Per-frame data of the program, from [`Handle::app`](crate::Handle::app).
 #[derive(Clone, Copy, Block)]
 #[repr(C)]
 pub struct App<D: BlockDom> {
+    /// Size of the whole image in pixels.
     pub size: D::UVec2,
+    /// Offset of the drawn tile into the image in pixels, from the bottom left
+    /// corner. Zero unless rendering in tiles.
+    pub tile_offset: D::UVec2,
+    /// Size of the drawn tile in pixels, equal to `size` unless rendering in
+    /// tiles.
+    pub tile_size: D::UVec2,
+    /// Offset of the samples of this sub-frame from the pixel centers in
+    /// pixels, see [`Handle::jitter`](crate::Handle::jitter).
+    pub jitter: D::Vec2,
+    /// Time of the frame in seconds, see [`Handle::time`](crate::Handle::time).
+    pub time: D::F32,
+    /// Position of the frame in the loop within `[0, 1)`, zero without a loop
+    /// duration, see [`Handle::loop_phase`](crate::Handle::loop_phase).
+    pub loop_phase: D::F32,
+}
+
+/// The clip space position in the whole image of `clip_space_pos` in the
+/// drawn tile, moved by the jitter of the sub-frame. Shaders that use it
+/// instead of the position of the tile look the same when rendered in tiles
+/// with [`Program::render_image`](crate::Program::render_image), and are
+/// anti-aliased when sub-frames are accumulated.
+#[must_use]
+pub fn image_clip_pos(clip_space_pos: Vec2, app: App<Sl>) -> Vec2 {
+    let pixel =
+        app.tile_offset.as_vec2() + uv(clip_space_pos) * app.tile_size.as_vec2() + app.jitter;
+    pixel / app.size.as_vec2() * 2.0 - 1.0
 }
 
 #[must_use]
@@ -46,6 +80,17 @@ pub const fn full_screen_quad() -> [gl::Vec2; 6] {
     ]
 }
 
+/// Vertex shader for [`Program::fullscreen`](crate::Program::fullscreen),
+/// drawing [`full_screen_quad`] and passing the UV coordinate, `(0, 0)` in
+/// the bottom left corner, to the fragment shader.
+#[must_use]
+pub fn fullscreen_vertex_shader(_: (), vertex: Vec2) -> sl::VsOutput<Vec2> {
+    sl::VsOutput {
+        clip_position: sl::vec4(vertex.x, vertex.y, 0.0, 1.0),
+        interpolant: uv(vertex),
+    }
+}
+
 pub fn texture_aspect_ratio<C: ColorSample>(sampler: sl::ColorSampler2d<C>) -> F32 {
     let size: Vec2 = sampler.size(0u32).as_vec2();
     aspect_ratio(size)
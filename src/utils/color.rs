@@ -0,0 +1,93 @@
+use posh::sl::{branch, Vec3, Vec4, F32};
+
+fn srgb_to_linear_channel(c: F32) -> F32 {
+    branch(c.le(0.04045), c / 12.92, ((c + 0.055) / 1.055).powf(2.4))
+}
+
+fn linear_to_srgb_channel(c: F32) -> F32 {
+    branch(
+        c.le(0.003_130_8),
+        c * 12.92,
+        c.powf(1.0 / 2.4) * 1.055 - 0.055,
+    )
+}
+
+/// Convert a gamma encoded sRGB color to linear RGB.
+#[must_use]
+pub fn srgb_to_linear(c: Vec3) -> Vec3 {
+    Vec3::new(
+        srgb_to_linear_channel(c.x),
+        srgb_to_linear_channel(c.y),
+        srgb_to_linear_channel(c.z),
+    )
+}
+
+/// Convert a linear RGB color to gamma encoded sRGB.
+#[must_use]
+pub fn linear_to_srgb(c: Vec3) -> Vec3 {
+    Vec3::new(
+        linear_to_srgb_channel(c.x),
+        linear_to_srgb_channel(c.y),
+        linear_to_srgb_channel(c.z),
+    )
+}
+
+/// Convert an HSV color (all components in `[0, 1]`) to RGB.
+#[must_use]
+pub fn hsv_to_rgb(c: Vec3) -> Vec3 {
+    let k = Vec3::splat(c.x * 6.0) + Vec3::new(0.0, 4.0, 2.0);
+    // GLSL `mod(k, 6.0)`
+    let k = k - (k / 6.0).floor() * 6.0;
+    let rgb = ((k - 3.0).abs() - 1.0).clamp(Vec3::splat(0.0), Vec3::splat(1.0));
+    Vec3::splat(1.0).lerp(rgb, c.y) * c.z
+}
+
+/// Convert an RGB color to HSV, with all components in `[0, 1]`.
+#[must_use]
+pub fn rgb_to_hsv(c: Vec3) -> Vec3 {
+    const EPSILON: f32 = 1.0e-10;
+
+    let p = branch(
+        c.y.ge(c.z),
+        Vec4::new(c.y, c.z, 0.0, -1.0 / 3.0),
+        Vec4::new(c.z, c.y, -1.0, 2.0 / 3.0),
+    );
+    let q = branch(
+        c.x.ge(p.x),
+        Vec4::new(c.x, p.y, p.z, p.x),
+        Vec4::new(p.x, p.y, p.w, c.x),
+    );
+    let d = q.x - q.w.min(q.y);
+    Vec3::new(
+        (q.z + (q.w - q.y) / (d * 6.0 + EPSILON)).abs(),
+        d / (q.x + EPSILON),
+        q.x,
+    )
+}
+
+/// Convert a linear RGB color to `OKLab`.
+#[must_use]
+pub fn linear_to_oklab(c: Vec3) -> Vec3 {
+    let l = (c.x * 0.412_221_46 + c.y * 0.536_332_55 + c.z * 0.051_445_995).powf(1.0 / 3.0);
+    let m = (c.x * 0.211_903_5 + c.y * 0.680_699_5 + c.z * 0.107_396_96).powf(1.0 / 3.0);
+    let s = (c.x * 0.088_302_46 + c.y * 0.281_718_85 + c.z * 0.629_978_7).powf(1.0 / 3.0);
+    Vec3::new(
+        l * 0.210_454_26 + m * 0.793_617_8 - s * 0.004_072_047,
+        l * 1.977_998_5 - m * 2.428_592_2 + s * 0.450_593_7,
+        l * 0.025_904_037 + m * 0.782_771_77 - s * 0.808_675_77,
+    )
+}
+
+/// Convert an `OKLab` color to linear RGB.
+#[must_use]
+pub const fn oklab_to_linear(c: Vec3) -> Vec3 {
+    let l = c.x + c.y * 0.396_337_78 + c.z * 0.215_803_76;
+    let m = c.x - c.y * 0.105_561_346 - c.z * 0.063_854_17;
+    let s = c.x - c.y * 0.089_484_18 - c.z * 1.291_485_5;
+    let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+    Vec3 {
+        x: l * 4.076_741_7 - m * 3.307_711_6 + s * 0.230_969_94,
+        y: l * -1.268_438 + m * 2.609_757_4 - s * 0.341_319_38,
+        z: l * -0.004_196_086_3 - m * 0.703_418_6 + s * 1.707_614_7,
+    }
+}
@@ -0,0 +1,167 @@
+use posh::sl::{branch, Vec2, Vec3, F32};
+
+/// Offsets of a cell and its eight neighbours.
+const CELLS: [[f32; 2]; 9] = [
+    [0.0, 0.0],
+    [-1.0, -1.0],
+    [0.0, -1.0],
+    [1.0, -1.0],
+    [-1.0, 0.0],
+    [1.0, 0.0],
+    [-1.0, 1.0],
+    [0.0, 1.0],
+    [1.0, 1.0],
+];
+
+/// Hash a 2D coordinate to a pseudo-random value in `[0, 1)`.
+#[must_use]
+pub fn hash12(p: Vec2) -> F32 {
+    let p3 = (Vec3::new(p.x, p.y, p.x) * 0.1031).fract();
+    let p3 = p3 + p3.dot(Vec3::new(p3.y, p3.z, p3.x) + 33.33);
+    ((p3.x + p3.y) * p3.z).fract()
+}
+
+/// Hash a 3D coordinate to a pseudo-random value in `[0, 1)`.
+#[must_use]
+pub fn hash13(p: Vec3) -> F32 {
+    let p3 = (p * 0.1031).fract();
+    let p3 = p3 + p3.dot(Vec3::new(p3.z, p3.y, p3.x) + 31.32);
+    ((p3.x + p3.y) * p3.z).fract()
+}
+
+/// Hash a 2D coordinate to a pseudo-random 2D vector in `[0, 1)`.
+#[must_use]
+pub fn hash22(p: Vec2) -> Vec2 {
+    let p3 = (Vec3::new(p.x, p.y, p.x) * Vec3::new(0.1031, 0.1030, 0.0973)).fract();
+    let p3 = p3 + p3.dot(Vec3::new(p3.y, p3.z, p3.x) + 33.33);
+    ((Vec2::new(p3.x, p3.x) + Vec2::new(p3.y, p3.z)) * Vec2::new(p3.z, p3.y)).fract()
+}
+
+/// Hash a 3D coordinate to a pseudo-random 3D vector in `[0, 1)`.
+#[must_use]
+pub fn hash33(p: Vec3) -> Vec3 {
+    let p3 = (p * Vec3::new(0.1031, 0.1030, 0.0973)).fract();
+    let p3 = p3 + p3.dot(Vec3::new(p3.y, p3.x, p3.z) + 33.33);
+    ((Vec3::new(p3.x, p3.x, p3.y) + Vec3::new(p3.y, p3.x, p3.x)) * Vec3::new(p3.z, p3.y, p3.x))
+        .fract()
+}
+
+/// Random unit-ish gradient in `[-1, 1]` for the lattice point `p`.
+fn gradient(p: Vec2) -> Vec2 {
+    hash22(p) * 2.0 - 1.0
+}
+
+/// Quintic fade curve used to interpolate between lattice points.
+const fn fade(t: Vec2) -> Vec2 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Value noise in `[0, 1]`.
+#[must_use]
+pub fn value_noise(p: Vec2) -> F32 {
+    let i = p.floor();
+    let f = p.fract();
+    let u = fade(f);
+
+    let a = hash12(i);
+    let b = hash12(i + Vec2::new(1.0, 0.0));
+    let c = hash12(i + Vec2::new(0.0, 1.0));
+    let d = hash12(i + Vec2::new(1.0, 1.0));
+
+    a.lerp(b, u.x).lerp(c.lerp(d, u.x), u.y)
+}
+
+/// 3D value noise in `[0, 1]`.
+#[must_use]
+pub fn value_noise3(p: Vec3) -> F32 {
+    let i = p.floor();
+    let f = p.fract();
+    let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    let corner = |x: f32, y: f32, z: f32| hash13(i + Vec3::new(x, y, z));
+    let bottom = corner(0.0, 0.0, 0.0)
+        .lerp(corner(1.0, 0.0, 0.0), u.x)
+        .lerp(corner(0.0, 1.0, 0.0).lerp(corner(1.0, 1.0, 0.0), u.x), u.y);
+    let top = corner(0.0, 0.0, 1.0)
+        .lerp(corner(1.0, 0.0, 1.0), u.x)
+        .lerp(corner(0.0, 1.0, 1.0).lerp(corner(1.0, 1.0, 1.0), u.x), u.y);
+
+    bottom.lerp(top, u.z)
+}
+
+/// Perlin gradient noise, roughly in `[-1, 1]`.
+#[must_use]
+pub fn perlin_noise(p: Vec2) -> F32 {
+    let i = p.floor();
+    let f = p.fract();
+    let u = fade(f);
+
+    let corner = |offset: Vec2| gradient(i + offset).dot(f - offset);
+    let a = corner(Vec2::new(0.0, 0.0));
+    let b = corner(Vec2::new(1.0, 0.0));
+    let c = corner(Vec2::new(0.0, 1.0));
+    let d = corner(Vec2::new(1.0, 1.0));
+
+    a.lerp(b, u.x).lerp(c.lerp(d, u.x), u.y)
+}
+
+/// Simplex noise, roughly in `[-1, 1]`.
+#[must_use]
+pub fn simplex_noise(p: Vec2) -> F32 {
+    // (sqrt(3) - 1) / 2 and (3 - sqrt(3)) / 6
+    const K1: f32 = 0.366_025_42;
+    const K2: f32 = 0.211_324_87;
+
+    let i = (p + (p.x + p.y) * K1).floor();
+    let a = p - i + (i.x + i.y) * K2;
+    let o = branch(a.x.gt(a.y), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0));
+    let b = a - o + K2;
+    let c = a - 1.0 + 2.0 * K2;
+
+    let h = (Vec3::splat(0.5) - Vec3::new(a.dot(a), b.dot(b), c.dot(c))).max(Vec3::splat(0.0));
+    let h4 = h * h * h * h;
+    let n = h4
+        * Vec3::new(
+            a.dot(gradient(i)),
+            b.dot(gradient(i + o)),
+            c.dot(gradient(i + 1.0)),
+        );
+
+    n.dot(Vec3::splat(70.0))
+}
+
+/// Worley (cellular) noise: distance to the closest feature point, roughly in `[0, 1]`.
+#[must_use]
+pub fn worley_noise(p: Vec2) -> F32 {
+    let i = p.floor();
+    let f = p.fract();
+
+    let cell = |[x, y]: [f32; 2]| {
+        let offset = Vec2::new(x, y);
+        (offset + hash22(i + offset) - f).length()
+    };
+    let [first, rest @ ..] = CELLS;
+    let mut distance = cell(first);
+    for offset in rest {
+        distance = distance.min(cell(offset));
+    }
+    distance
+}
+
+/// Fractal Brownian motion: sums `octaves` layers of `noise`, doubling the
+/// frequency and halving the amplitude at each layer.
+///
+/// `octaves` is clamped to at least one layer. The loop is unrolled when the
+/// shader is generated, so keep it small.
+#[must_use]
+pub fn fbm(p: Vec2, octaves: u32, noise: impl Fn(Vec2) -> F32) -> F32 {
+    let mut amplitude = 0.5;
+    let mut frequency = 2.0;
+    let mut value = noise(p) * amplitude;
+    for _ in 1..octaves {
+        amplitude *= 0.5;
+        value = value + noise(p * frequency) * amplitude;
+        frequency *= 2.0;
+    }
+    value
+}
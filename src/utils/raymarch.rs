@@ -0,0 +1,175 @@
+use posh::sl::{branch, Bool, Vec2, Vec3, Vec4, F32};
+use posh::Sl;
+
+use super::{aspect_ratio, image_clip_pos, App};
+
+/// A ray with a normalized direction.
+#[derive(Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    #[must_use]
+    pub fn at(&self, t: F32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+}
+
+/// A pinhole camera looking from `position` at `target`.
+///
+/// `fov` is the vertical field of view in radians.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub fov: F32,
+}
+
+impl Camera {
+    /// Create the primary ray for the given clip space position of the drawn
+    /// tile, correcting for the aspect ratio of the image.
+    #[must_use]
+    pub fn ray(&self, clip_space_pos: Vec2, app: App<Sl>) -> Ray {
+        let clip_space_pos = image_clip_pos(clip_space_pos, app);
+        let aspect = aspect_ratio(app.size.as_vec2());
+        let forward = (self.target - self.position).normalize();
+        let right = forward.cross(Vec3::new(0.0, 1.0, 0.0)).normalize();
+        let up = right.cross(forward);
+        let scale = (self.fov * 0.5).tan();
+        let direction =
+            forward + right * (clip_space_pos.x * aspect * scale) + up * (clip_space_pos.y * scale);
+        Ray {
+            origin: self.position,
+            direction: direction.normalize(),
+        }
+    }
+}
+
+/// Settings for [`march`].
+///
+/// Shaders have no dynamic loops, so every step is unrolled when the shader
+/// is generated. Keep `max_steps` as low as the scene allows.
+#[derive(Debug, Clone, Copy)]
+pub struct MarchSettings {
+    pub max_steps: u32,
+    pub max_distance: f32,
+    pub epsilon: f32,
+}
+
+impl Default for MarchSettings {
+    fn default() -> Self {
+        Self {
+            max_steps: 64,
+            max_distance: 100.0,
+            epsilon: 0.001,
+        }
+    }
+}
+
+/// Result of [`march`].
+#[derive(Clone, Copy)]
+pub struct Hit {
+    /// Whether the ray hit a surface before `max_distance`.
+    pub hit: Bool,
+    /// Distance travelled along the ray.
+    pub distance: F32,
+    pub position: Vec3,
+}
+
+/// Sphere trace `sdf` along `ray`.
+#[must_use]
+pub fn march(ray: Ray, settings: &MarchSettings, sdf: impl Fn(Vec3) -> F32) -> Hit {
+    let mut t = sdf(ray.origin);
+    for _ in 1..settings.max_steps {
+        let d = sdf(ray.at(t));
+        // Freeze `t` once we either converged or left the scene.
+        let step = branch(
+            d.lt(settings.epsilon * 0.5),
+            0.0,
+            branch(t.gt(settings.max_distance), 0.0, d),
+        );
+        t = t + step;
+    }
+    Hit {
+        hit: t.lt(settings.max_distance),
+        distance: t,
+        position: ray.at(t),
+    }
+}
+
+/// Estimate the surface normal of `sdf` at `p` using the tetrahedron technique.
+#[must_use]
+pub fn normal(p: Vec3, sdf: impl Fn(Vec3) -> F32) -> Vec3 {
+    const H: f32 = 0.0005;
+    let k = |x: f32, y: f32, z: f32| Vec3::new(x, y, z);
+    let sample = |dir: Vec3| dir * sdf(p + dir * H);
+    (sample(k(1.0, -1.0, -1.0))
+        + sample(k(-1.0, -1.0, 1.0))
+        + sample(k(-1.0, 1.0, -1.0))
+        + sample(k(1.0, 1.0, 1.0)))
+    .normalize()
+}
+
+/// Soft shadow factor in `[0, 1]` for a ray from `origin` towards a light in
+/// `direction`. Larger `k` gives harder shadows.
+#[must_use]
+pub fn soft_shadow(
+    origin: Vec3,
+    direction: Vec3,
+    min_t: f32,
+    k: f32,
+    steps: u32,
+    sdf: impl Fn(Vec3) -> F32,
+) -> F32 {
+    let h = sdf(origin + direction * min_t);
+    let mut result = h * k / min_t;
+    let mut t = h.clamp(0.02, 0.1) + min_t;
+    for _ in 1..steps {
+        let h = sdf(origin + direction * t);
+        result = result.min(h * k / t);
+        t = t + h.clamp(0.02, 0.1);
+    }
+    result.clamp(0.0, 1.0)
+}
+
+/// Ambient occlusion factor in `[0, 1]` at `p` with surface normal `normal`.
+#[must_use]
+pub fn ambient_occlusion(p: Vec3, normal: Vec3, sdf: impl Fn(Vec3) -> F32) -> F32 {
+    let mut h = 0.01;
+    let mut scale = 1.0;
+    let mut occlusion = (sdf(p + normal * h) * -1.0 + h) * scale;
+    for _ in 1..5 {
+        h += 0.03;
+        scale *= 0.95;
+        occlusion = occlusion + (sdf(p + normal * h) * -1.0 + h) * scale;
+    }
+    (occlusion * -3.0 + 1.0).clamp(0.0, 1.0)
+}
+
+/// Render `sdf` from `camera` with a default directional light, soft shadows,
+/// ambient occlusion and a sky gradient. Intended to be returned straight from
+/// a fragment shader drawn over [`full_screen_quad`](super::full_screen_quad).
+#[must_use]
+pub fn render(
+    camera: &Camera,
+    clip_space_pos: Vec2,
+    app: App<Sl>,
+    sdf: impl Fn(Vec3) -> F32 + Copy,
+) -> Vec4 {
+    let settings = MarchSettings::default();
+    let ray = camera.ray(clip_space_pos, app);
+    let hit = march(ray, &settings, sdf);
+
+    let light = Vec3::new(0.6, 0.7, -0.5).normalize();
+    let n = normal(hit.position, sdf);
+    let diffuse = n.dot(light).max(0.0);
+    let shadow = soft_shadow(hit.position + n * 0.01, light, 0.02, 8.0, 32, sdf);
+    let occlusion = ambient_occlusion(hit.position, n, sdf);
+    let color = Vec3::splat(0.9) * diffuse * shadow + Vec3::new(0.1, 0.12, 0.15) * occlusion;
+
+    let sky = Vec3::new(0.7, 0.8, 1.0).lerp(Vec3::new(0.3, 0.5, 0.9), ray.direction.y.max(0.0));
+    let color = branch(hit.hit, color, sky);
+    Vec4::new(color.x, color.y, color.z, 1.0)
+}
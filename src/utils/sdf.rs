@@ -0,0 +1,126 @@
+use posh::sl::{Vec2, Vec3, F32};
+
+/// Signed distance to a circle of radius `r` centered at the origin.
+#[must_use]
+pub fn sd_circle(p: Vec2, r: F32) -> F32 {
+    p.length() - r
+}
+
+/// Signed distance to an axis aligned box with half extents `b`.
+#[must_use]
+pub fn sd_box2(p: Vec2, b: Vec2) -> F32 {
+    let d = p.abs() - b;
+    d.max(Vec2::splat(0.0)).length() + d.x.max(d.y).min(0.0)
+}
+
+/// Signed distance to a box with half extents `b` and corners rounded by `r`.
+#[must_use]
+pub fn sd_rounded_box2(p: Vec2, b: Vec2, r: F32) -> F32 {
+    sd_box2(p, b - r) - r
+}
+
+/// Distance to the line segment between `a` and `b`.
+#[must_use]
+pub fn sd_segment(p: Vec2, a: Vec2, b: Vec2) -> F32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+    (pa - ba * h).length()
+}
+
+/// Signed distance to a sphere of radius `r` centered at the origin.
+#[must_use]
+pub fn sd_sphere(p: Vec3, r: F32) -> F32 {
+    p.length() - r
+}
+
+/// Signed distance to an axis aligned box with half extents `b`.
+#[must_use]
+pub fn sd_box(p: Vec3, b: Vec3) -> F32 {
+    let d = p.abs() - b;
+    d.max(Vec3::splat(0.0)).length() + d.x.max(d.y.max(d.z)).min(0.0)
+}
+
+/// Signed distance to a box with half extents `b` and edges rounded by `r`.
+#[must_use]
+pub fn sd_rounded_box(p: Vec3, b: Vec3, r: F32) -> F32 {
+    sd_box(p, b - r) - r
+}
+
+/// Signed distance to a torus lying in the XZ plane. `t.x` is the major
+/// radius and `t.y` the minor radius.
+#[must_use]
+pub fn sd_torus(p: Vec3, t: Vec2) -> F32 {
+    let q = Vec2::new(Vec2::new(p.x, p.z).length() - t.x, p.y);
+    q.length() - t.y
+}
+
+/// Signed distance to a plane with unit normal `n`, offset `h` from the origin.
+#[must_use]
+pub fn sd_plane(p: Vec3, n: Vec3, h: F32) -> F32 {
+    p.dot(n) + h
+}
+
+/// Signed distance to a capsule of radius `r` between `a` and `b`.
+#[must_use]
+pub fn sd_capsule(p: Vec3, a: Vec3, b: Vec3, r: F32) -> F32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+    (pa - ba * h).length() - r
+}
+
+/// Signed distance to a Y aligned cylinder of radius `r` and half height `h`.
+#[must_use]
+pub fn sd_cylinder(p: Vec3, r: F32, h: F32) -> F32 {
+    let d = Vec2::new(Vec2::new(p.x, p.z).length(), p.y).abs() - Vec2::new(r, h);
+    d.x.max(d.y).min(0.0) + d.max(Vec2::splat(0.0)).length()
+}
+
+#[must_use]
+pub fn op_union(d1: F32, d2: F32) -> F32 {
+    d1.min(d2)
+}
+
+#[must_use]
+pub fn op_intersection(d1: F32, d2: F32) -> F32 {
+    d1.max(d2)
+}
+
+/// Subtract the shape `d2` from the shape `d1`.
+#[must_use]
+pub fn op_subtraction(d1: F32, d2: F32) -> F32 {
+    d1.max(d2 * -1.0)
+}
+
+/// Union blended over a distance of `k`.
+#[must_use]
+pub fn op_smooth_union(d1: F32, d2: F32, k: F32) -> F32 {
+    let h = ((d2 - d1) / k * 0.5 + 0.5).clamp(0.0, 1.0);
+    d2.lerp(d1, h) - k * h * (1.0 - h)
+}
+
+/// Intersection blended over a distance of `k`.
+#[must_use]
+pub fn op_smooth_intersection(d1: F32, d2: F32, k: F32) -> F32 {
+    let h = ((d2 - d1) / k * -0.5 + 0.5).clamp(0.0, 1.0);
+    d2.lerp(d1, h) + k * h * (1.0 - h)
+}
+
+/// Subtraction of `d2` from `d1` blended over a distance of `k`.
+#[must_use]
+pub fn op_smooth_subtraction(d1: F32, d2: F32, k: F32) -> F32 {
+    op_smooth_intersection(d1, d2 * -1.0, k)
+}
+
+/// Round the edges of a shape by `r`.
+#[must_use]
+pub const fn op_round(d: F32, r: F32) -> F32 {
+    d - r
+}
+
+/// Turn a solid shape into a shell of thickness `r`.
+#[must_use]
+pub fn op_onion(d: F32, r: F32) -> F32 {
+    d.abs() - r
+}
@@ -0,0 +1,57 @@
+use posh::sl::{Mat2, Mat3, Vec2, Vec3, F32};
+
+/// Counter-clockwise 2D rotation by `angle` radians.
+#[must_use]
+pub fn rotate2(angle: F32) -> Mat2 {
+    let (s, c) = (angle.sin(), angle.cos());
+    Mat2 {
+        x_axis: Vec2::new(c, s),
+        y_axis: Vec2::new(s * -1.0, c),
+    }
+}
+
+/// Rotation around the X axis by `angle` radians.
+#[must_use]
+pub fn rotate_x(angle: F32) -> Mat3 {
+    let (s, c) = (angle.sin(), angle.cos());
+    Mat3 {
+        x_axis: Vec3::new(1.0, 0.0, 0.0),
+        y_axis: Vec3::new(0.0, c, s),
+        z_axis: Vec3::new(0.0, s * -1.0, c),
+    }
+}
+
+/// Rotation around the Y axis by `angle` radians.
+#[must_use]
+pub fn rotate_y(angle: F32) -> Mat3 {
+    let (s, c) = (angle.sin(), angle.cos());
+    Mat3 {
+        x_axis: Vec3::new(c, 0.0, s * -1.0),
+        y_axis: Vec3::new(0.0, 1.0, 0.0),
+        z_axis: Vec3::new(s, 0.0, c),
+    }
+}
+
+/// Rotation around the Z axis by `angle` radians.
+#[must_use]
+pub fn rotate_z(angle: F32) -> Mat3 {
+    let (s, c) = (angle.sin(), angle.cos());
+    Mat3 {
+        x_axis: Vec3::new(c, s, 0.0),
+        y_axis: Vec3::new(s * -1.0, c, 0.0),
+        z_axis: Vec3::new(0.0, 0.0, 1.0),
+    }
+}
+
+/// Rotation around the unit vector `axis` by `angle` radians.
+#[must_use]
+pub fn rotate_axis(axis: Vec3, angle: F32) -> Mat3 {
+    let (s, c) = (angle.sin(), angle.cos());
+    let t = 1.0 - c;
+    let Vec3 { x, y, z } = axis;
+    Mat3 {
+        x_axis: Vec3::new(t * x * x + c, t * x * y + s * z, t * x * z - s * y),
+        y_axis: Vec3::new(t * x * y - s * z, t * y * y + c, t * y * z + s * x),
+        z_axis: Vec3::new(t * x * z + s * y, t * y * z - s * x, t * z * z + c),
+    }
+}
@@ -0,0 +1,195 @@
+//! Video output, encoded by piping raw frames into an `ffmpeg` process.
+
+use std::{
+    ffi::OsStr,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    thread::{self, JoinHandle},
+};
+
+use image::RgbaImage;
+use winit::dpi::PhysicalSize;
+
+use crate::error::{log_error, ErrKind, VideoFileError};
+
+/// How headless frames are encoded into a video by `ffmpeg`, which has to be
+/// installed separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoConfig {
+    /// The `ffmpeg` executable, looked up in `PATH` unless it is a path.
+    pub ffmpeg: PathBuf,
+    /// Video codec, e.g. `libx264` or `libvpx-vp9`. `ffmpeg` picks one for
+    /// the container when `None`.
+    pub codec: Option<String>,
+    /// Target bitrate in `ffmpeg` notation, e.g. `8M`.
+    pub bitrate: Option<String>,
+    /// Pixel format of the video. Defaults to `yuv420p`, which every player
+    /// supports, for every container but GIF.
+    pub pixel_format: Option<String>,
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            ffmpeg: "ffmpeg".into(),
+            codec: None,
+            bitrate: None,
+            pixel_format: None,
+        }
+    }
+}
+
+const VIDEO_EXTENSIONS: [&str; 5] = ["mp4", "mov", "mkv", "webm", "gif"];
+
+/// Whether `path` has the extension of a video container `ffmpeg` can write.
+#[must_use]
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// A running `ffmpeg` process encoding the frames written to it.
+pub(crate) struct Encoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    /// Collects the error output, so `ffmpeg` never blocks on a full pipe.
+    stderr: Option<JoinHandle<String>>,
+    output: PathBuf,
+}
+
+impl Encoder {
+    /// Start encoding frames of `size` at `framerate` into `output`,
+    /// overwriting it.
+    pub(crate) fn spawn(
+        config: &VideoConfig,
+        size: PhysicalSize<u32>,
+        framerate: f32,
+        output: &Path,
+    ) -> Result<Self, ErrKind> {
+        let mut command = Command::new(&config.ffmpeg);
+        command
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .arg("-s")
+            .arg(format!("{}x{}", size.width, size.height))
+            .arg("-r")
+            .arg(framerate.to_string())
+            .args(["-i", "-"]);
+        if let Some(codec) = &config.codec {
+            command.arg("-c:v").arg(codec);
+        }
+        if let Some(bitrate) = &config.bitrate {
+            command.arg("-b:v").arg(bitrate);
+        }
+        let gif = output
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+        let default_format = (!gif).then_some("yuv420p");
+        if let Some(pixel_format) = config.pixel_format.as_deref().or(default_format) {
+            // Chroma is subsampled by two in both directions
+            if pixel_format.starts_with("yuv420")
+                && (!size.width.is_multiple_of(2) || !size.height.is_multiple_of(2))
+            {
+                return Err(ErrKind::InvalidRenderConfig(format!(
+                    "{pixel_format} video needs an even size, got {}x{}",
+                    size.width, size.height
+                )));
+            }
+            command.arg("-pix_fmt").arg(pixel_format);
+        }
+        command
+            .arg(output)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        let mut child = command.spawn().map_err(|source| {
+            if source.kind() == io::ErrorKind::NotFound {
+                ErrKind::FfmpegNotFound {
+                    program: config.ffmpeg.clone(),
+                }
+            } else {
+                ErrKind::VideoError {
+                    path: output.into(),
+                    source: source.into(),
+                }
+            }
+        })?;
+        let stderr = child.stderr.take().map(|mut pipe| {
+            thread::spawn(move || {
+                let mut stderr = String::new();
+                let _ = pipe.read_to_string(&mut stderr);
+                stderr
+            })
+        });
+        info!("Encoding video to {} with ffmpeg", output.display());
+        Ok(Self {
+            stdin: child.stdin.take(),
+            child,
+            stderr,
+            output: output.into(),
+        })
+    }
+
+    /// Encode the next frame.
+    pub(crate) fn write_frame(&mut self, frame: &RgbaImage) -> Result<(), ErrKind> {
+        let written = match self.stdin.as_mut() {
+            Some(stdin) => stdin.write_all(frame),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        };
+        match written {
+            Ok(()) => Ok(()),
+            // ffmpeg quit early, its own error says why
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+                self.stdin = None;
+                self.wait()?;
+                Err(self.error(err.into()))
+            }
+            Err(err) => Err(self.error(err.into())),
+        }
+    }
+
+    /// Finish the video and wait for `ffmpeg` to write it.
+    pub(crate) fn finish(mut self) -> Result<(), ErrKind> {
+        // Closing stdin ends the input
+        self.stdin = None;
+        self.wait()?;
+        info!("Video saved to {}", self.output.display());
+        Ok(())
+    }
+
+    /// Wait for `ffmpeg` to exit, failing with its error output unless it
+    /// succeeded.
+    fn wait(&mut self) -> Result<(), ErrKind> {
+        let status = self.child.wait().map_err(|err| self.error(err.into()))?;
+        if status.success() {
+            return Ok(());
+        }
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|thread| thread.join().ok())
+            .unwrap_or_default();
+        Err(self.error(VideoFileError::Ffmpeg {
+            status,
+            stderr: stderr.trim().to_owned(),
+        }))
+    }
+
+    fn error(&self, source: VideoFileError) -> ErrKind {
+        ErrKind::VideoError {
+            path: self.output.clone(),
+            source,
+        }
+    }
+}
+
+impl Drop for Encoder {
+    /// Reap `ffmpeg` if encoding was cut short, e.g. by a failed frame.
+    fn drop(&mut self) {
+        self.stdin = None;
+        log_error(self.child.wait());
+    }
+}
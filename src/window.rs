@@ -0,0 +1,606 @@
+//! The window loop, driving one or more windows that share one OpenGL context
+//! and one event loop.
+
+use std::{
+    num::NonZeroU32,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use glutin::{
+    context::{PossiblyCurrentContext, PossiblyCurrentGlContext},
+    surface::{GlSurface, Surface, WindowSurface},
+};
+use posh::{
+    sl::{self, ColorSample, FsFunc, FsSig, VsFunc, VsSig},
+    Sl, UniformInterface, UniformUnion, VsInterface,
+};
+use winit::{
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    keyboard::{KeyCode, PhysicalKey},
+    platform::pump_events::{EventLoopExtPumpEvents, PumpStatus},
+    window::{Window, WindowBuilder, WindowId},
+};
+
+#[cfg(feature = "gui")]
+use crate::gui;
+use crate::{
+    app::AppHandler,
+    capture, create_surface,
+    error::{log_error, ErrKind, InitStep},
+    offline,
+    params::Params,
+    preset::Preset,
+    stage::{Missing, SettingsStage, UniformStage, VertexStage},
+    DrawMode, Handle, Program, ProgramState, RunMode, WindowConfig, WindowState,
+    DEFAULT_PRESET_PATH, SAVE_PRESET_KEY,
+};
+
+/// A window and everything the window loop needs to draw to it.
+struct WindowLoop {
+    window: Window,
+    gl_surface: Surface<WindowSurface>,
+    ctx: Rc<PossiblyCurrentContext>,
+    #[cfg(feature = "gui")]
+    overlay: Option<gui::Overlay>,
+    /// Where F5 saves the parameters. `None` for extra windows, which follow
+    /// the first window's parameters and must not overwrite its preset.
+    preset_path: Option<PathBuf>,
+    /// `None` when drawing once, in which case frames are only drawn when the
+    /// window asks for a redraw.
+    frame_interval: Option<Duration>,
+    next_frame: Instant,
+    screenshot_key: Option<KeyCode>,
+    /// Save a screenshot after the next frame is drawn.
+    screenshot: bool,
+    loop_duration: Option<Duration>,
+}
+
+enum WindowStatus {
+    Open,
+    Closed,
+}
+
+/// A program and its window, as driven by the window loop.
+trait LoopWindow {
+    fn id(&self) -> WindowId;
+
+    /// Request a redraw if a frame is due, and return when the next one is.
+    fn schedule(&mut self, now: Instant) -> Option<Instant>;
+
+    fn window_event(&mut self, event: WindowEvent) -> Result<WindowStatus, ErrKind>;
+
+    fn params(&self) -> &Params;
+
+    fn params_mut(&mut self) -> &mut Params;
+}
+
+struct Served<P, H> {
+    program: P,
+    window: WindowLoop,
+    handler: H,
+}
+
+impl<U, V, Vx, Un, St, H> LoopWindow for Served<Program<U, V, sl::Vec4, Vx, Un, St>, H>
+where
+    U: UniformInterface<Sl> + 'static,
+    V: VsInterface<Sl> + 'static,
+    Vx: VertexStage<V>,
+    Un: UniformStage<U>,
+    St: SettingsStage,
+    H: AppHandler,
+{
+    fn id(&self) -> WindowId {
+        self.window.window.id()
+    }
+
+    fn schedule(&mut self, now: Instant) -> Option<Instant> {
+        let interval = self.window.frame_interval?;
+        if now >= self.window.next_frame {
+            self.window.window.request_redraw();
+            self.window.next_frame = now + interval;
+        }
+        Some(self.window.next_frame)
+    }
+
+    fn window_event(&mut self, event: WindowEvent) -> Result<WindowStatus, ErrKind> {
+        self.program
+            .on_window_event(&mut self.window, &mut self.handler, event)
+    }
+
+    fn params(&self) -> &Params {
+        &self.program.state.params
+    }
+
+    fn params_mut(&mut self) -> &mut Params {
+        &mut self.program.state.params
+    }
+}
+
+/// The windows driven by one event loop.
+struct Windows<'a> {
+    /// The program that owns the event loop comes first.
+    windows: Vec<Box<dyn LoopWindow + 'a>>,
+    /// The error of a window, which ends the loop.
+    result: Result<(), ErrKind>,
+}
+
+impl<'a> Windows<'a> {
+    fn new(first: Box<dyn LoopWindow + 'a>, extra: Vec<ExtraWindow>) -> Self {
+        let mut windows = vec![first];
+        for ExtraWindow(window) in extra {
+            windows.push(window);
+        }
+        for window in &mut windows {
+            window.schedule(Instant::now());
+        }
+        Self {
+            windows,
+            result: Ok(()),
+        }
+    }
+
+    fn on_event(&mut self, event: Event<()>, target: &EventLoopWindowTarget<()>) {
+        match event {
+            Event::AboutToWait => {
+                let now = Instant::now();
+                let next_frame = self
+                    .windows
+                    .iter_mut()
+                    .filter_map(|window| window.schedule(now))
+                    .min();
+                target
+                    .set_control_flow(next_frame.map_or(ControlFlow::Wait, ControlFlow::WaitUntil));
+            }
+            Event::WindowEvent { window_id, event } => {
+                let Some(index) = self
+                    .windows
+                    .iter()
+                    .position(|window| window.id() == window_id)
+                else {
+                    return;
+                };
+                // Extra windows follow the parameters of the first one, which
+                // may have been changed through its overlay
+                if let [first, rest @ ..] = self.windows.as_mut_slice() {
+                    if let Some(window) = index.checked_sub(1).and_then(|i| rest.get_mut(i)) {
+                        window.params_mut().copy_values(first.params());
+                    }
+                }
+                match self.windows[index].window_event(event) {
+                    Ok(WindowStatus::Open) => {}
+                    Ok(WindowStatus::Closed) if index == 0 => target.exit(),
+                    Ok(WindowStatus::Closed) => {
+                        self.windows.remove(index);
+                    }
+                    Err(err) => {
+                        self.result = Err(err);
+                        target.exit();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The window loop of a program, stepped by a foreign loop instead of
+/// running on its own. Created with [`Program::into_stepper`].
+///
+/// Stepping relies on winit's `pump_events`, which is not supported on every
+/// platform.
+pub struct WindowStepper {
+    event_loop: EventLoop<()>,
+    windows: Windows<'static>,
+}
+
+impl WindowStepper {
+    /// Handle the pending events and draw the frames that are due, waiting
+    /// up to `timeout` for events, or until the next frame is due if `None`.
+    /// Returns [`PumpStatus::Exit`] once the program's window is closed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if drawing any of the windows
+    /// fails, which also exits the loop.
+    pub fn pump(&mut self, timeout: Option<Duration>) -> Result<PumpStatus, ErrKind> {
+        let windows = &mut self.windows;
+        let status = self
+            .event_loop
+            .pump_events(timeout, |event, target| windows.on_event(event, target));
+        std::mem::replace(&mut self.windows.result, Ok(()))?;
+        Ok(status)
+    }
+}
+
+/// A program drawing to its own window, opened with [`Program::open_window`]
+/// and served together with the program that opened it by
+/// [`Program::run_with`].
+pub struct ExtraWindow(Box<dyn LoopWindow>);
+
+impl<U, V, F, Vx, Un, St> Program<U, V, F, Vx, Un, St>
+where
+    U: UniformInterface<Sl> + 'static,
+    V: VsInterface<Sl> + 'static,
+    F: ColorSample,
+{
+    /// Open another window sharing this program's OpenGL context and event
+    /// loop, drawn by a program of its own. Serve it with
+    /// [`Program::run_with`], after turning it into an [`ExtraWindow`] with
+    /// [`Program::into_window`].
+    ///
+    /// Since the context is shared, buffers and textures created through
+    /// either [`Handle`] can be used by both programs. Extra windows don't
+    /// show the parameter overlay. They start out with this program's
+    /// parameters and keep following the values of those this program
+    /// registers under the same name, so the overlay of this program's
+    /// window controls both. For the same reason, only this program's
+    /// window saves its preset with F5, the preset of an extra window is
+    /// only read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ErrKind::NoWindow`] if this program
+    /// doesn't own an event loop, and an error if the window, its surface or
+    /// the shader program can't be created.
+    pub fn open_window<U2, V2, F2, FFn, VFn, FSig, VSig>(
+        &self,
+        vertex_shader: VFn,
+        fragment_shader: FFn,
+        config: WindowConfig,
+    ) -> Result<Program<U2, V2, F2>, ErrKind>
+    where
+        U2: UniformInterface<Sl> + 'static,
+        V2: VsInterface<Sl> + 'static,
+        F2: ColorSample,
+        VSig: VsSig<C = (), V = V2>,
+        FSig: FsSig<C = (), W = VSig::W, F = F2>,
+        VFn: VsFunc<VSig>,
+        FFn: FsFunc<FSig>,
+        U2: UniformUnion<VSig::U, FSig::U>,
+    {
+        let Some(WindowState {
+            config: gl_config,
+            event_loop: Some(event_loop),
+            ctx,
+            srgb,
+            ..
+        }) = self.state.window.as_ref()
+        else {
+            return Err(ErrKind::NoWindow);
+        };
+        let window_builder = WindowBuilder::new()
+            .with_title(&config.title)
+            .with_inner_size(config.size)
+            .with_transparent(self.state.framebuffer.alpha);
+        let window = glutin_winit::finalize_window(event_loop, window_builder.clone(), gl_config)
+            .map_err(ErrKind::WindowError)?;
+        let gl_surface = create_surface(gl_config, &window, *srgb)?;
+        let state = self.state.sibling(WindowState {
+            config: gl_config.clone(),
+            event_loop: None,
+            gl_surface,
+            window_builder,
+            window,
+            ctx: ctx.clone(),
+            srgb: *srgb,
+        });
+        let inner = state.gl.create_program(vertex_shader, fragment_shader)?;
+        Ok(Program {
+            state,
+            run_mode: RunMode::Windowed(Some(config)),
+            inner,
+            vertices: Missing,
+            uniforms: Missing,
+            settings: Missing,
+            frame_stats: None,
+        })
+    }
+}
+
+impl<U, V, Vx, Un, St> Program<U, V, sl::Vec4, Vx, Un, St>
+where
+    U: UniformInterface<Sl> + 'static,
+    V: VsInterface<Sl> + 'static,
+    Vx: VertexStage<V>,
+    Un: UniformStage<U>,
+    St: SettingsStage,
+{
+    /// Start the program like [`Program::run`], together with `windows`
+    /// opened with [`Program::open_window`], all driven by this program's
+    /// event loop.
+    ///
+    /// Closing this program's window ends the loop, closing any of the other
+    /// windows only closes that window. In [`RunMode::Headless`] the frames
+    /// are rendered with [`Program::render_image`] and written to the output
+    /// instead, and `windows` are ignored.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the event loop fails or drawing
+    /// any of the windows fails.
+    pub fn run_with(
+        mut self,
+        mut handler: impl AppHandler,
+        windows: Vec<ExtraWindow>,
+    ) -> Result<(), ErrKind> {
+        self.state.load_params(&self.run_mode)?;
+        match self.run_mode {
+            RunMode::Headless(ref render_config) => {
+                let render_config = render_config.clone().unwrap_or_default();
+                handler.setup(Handle(&self.state));
+                self.render_output(&mut handler, &render_config)
+            }
+            RunMode::Windowed(ref window_config) => {
+                let window_config = window_config.clone().unwrap_or_default();
+                self.window_loop(&window_config, &mut handler, windows)
+            }
+        }
+    }
+
+    /// Prepare a program opened with [`Program::open_window`] to be served by
+    /// [`Program::run_with`], with `handler` driving it like the handler of
+    /// [`Program::run`]. Its setup runs right away.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a parameter assignment or the
+    /// preset is invalid, or the program has no window.
+    pub fn into_window(
+        mut self,
+        mut handler: impl AppHandler + 'static,
+    ) -> Result<ExtraWindow, ErrKind>
+    where
+        Vx: 'static,
+        Un: 'static,
+        St: 'static,
+    {
+        self.state.load_params(&self.run_mode)?;
+        let config = match &self.run_mode {
+            RunMode::Windowed(Some(config)) => config.clone(),
+            _ => WindowConfig::default(),
+        };
+        let window = self.take_window(&config, None)?;
+        handler.setup(Handle(&self.state));
+        Ok(ExtraWindow(Box::new(Served {
+            program: self,
+            window,
+            handler,
+        })))
+    }
+
+    /// Move the window out of the program state for the window loop.
+    fn take_window(
+        &mut self,
+        config: &WindowConfig,
+        event_loop: Option<&EventLoop<()>>,
+    ) -> Result<WindowLoop, ErrKind> {
+        let Some(WindowState {
+            gl_surface,
+            window,
+            ctx,
+            ..
+        }) = self.state.window.take()
+        else {
+            return Err(ErrKind::NoWindow);
+        };
+        Ok(WindowLoop {
+            #[cfg(feature = "gui")]
+            overlay: event_loop
+                .filter(|_| !self.state.params.is_empty())
+                .and_then(|event_loop| self.state.create_overlay(event_loop)),
+            window,
+            gl_surface,
+            ctx,
+            preset_path: event_loop.map(|_| {
+                config
+                    .preset
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_PRESET_PATH.into())
+            }),
+            frame_interval: match config.draw_mode {
+                DrawMode::Once => None,
+                DrawMode::Loop { framerate } => Some(Duration::from_secs_f32(1.0 / framerate)),
+            },
+            next_frame: Instant::now(),
+            screenshot_key: config.screenshot_key,
+            screenshot: false,
+            loop_duration: config.loop_duration,
+        })
+    }
+
+    /// Start the program like [`Program::run_with`], but hand the window
+    /// loop to a foreign loop, which steps it with [`WindowStepper::pump`],
+    /// instead of running it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ErrKind::NoWindow`] in
+    /// [`RunMode::Headless`] or if this program doesn't own an event loop,
+    /// and an error if a parameter assignment or the preset is invalid.
+    pub fn into_stepper(
+        mut self,
+        mut handler: impl AppHandler + 'static,
+        windows: Vec<ExtraWindow>,
+    ) -> Result<WindowStepper, ErrKind>
+    where
+        Vx: 'static,
+        Un: 'static,
+        St: 'static,
+    {
+        self.state.load_params(&self.run_mode)?;
+        let RunMode::Windowed(config) = &self.run_mode else {
+            return Err(ErrKind::NoWindow);
+        };
+        let config = config.clone().unwrap_or_default();
+        let (event_loop, window) = self.start_window(&config, &mut handler)?;
+        let first = Box::new(Served {
+            program: self,
+            window,
+            handler,
+        });
+        Ok(WindowStepper {
+            event_loop,
+            windows: Windows::new(first, windows),
+        })
+    }
+
+    /// Take the event loop and the window, and set up the handler before the
+    /// first frame.
+    fn start_window(
+        &mut self,
+        config: &WindowConfig,
+        handler: &mut impl AppHandler,
+    ) -> Result<(EventLoop<()>, WindowLoop), ErrKind> {
+        let Some(event_loop) = self
+            .state
+            .window
+            .as_mut()
+            .and_then(|window| window.event_loop.take())
+        else {
+            return Err(ErrKind::NoWindow);
+        };
+        let window = self.take_window(config, Some(&event_loop))?;
+        #[cfg(feature = "audio-playback")]
+        if config.play_audio {
+            self.state.start_playback();
+        }
+        handler.setup(Handle(&self.state));
+        Ok((event_loop, window))
+    }
+
+    fn window_loop(
+        mut self,
+        config: &WindowConfig,
+        handler: &mut impl AppHandler,
+        extra: Vec<ExtraWindow>,
+    ) -> Result<(), ErrKind> {
+        let (event_loop, window) = self.start_window(config, handler)?;
+        let first = Box::new(Served {
+            program: self,
+            window,
+            handler,
+        });
+        let mut windows = Windows::new(first, extra);
+        event_loop.run(|event, target| windows.on_event(event, target))?;
+        windows.result
+    }
+
+    fn on_window_event(
+        &mut self,
+        window: &mut WindowLoop,
+        handler: &mut impl AppHandler,
+        event: WindowEvent,
+    ) -> Result<WindowStatus, ErrKind> {
+        // Checked first, the overlay would consume the key while focused
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(SAVE_PRESET_KEY),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+            ..
+        } = event
+        {
+            if let Some(preset_path) = &window.preset_path {
+                let saved = Preset::from_params(&self.state.params).save(preset_path);
+                if saved.is_ok() {
+                    info!("Preset saved to {}", preset_path.display());
+                }
+                log_error(saved);
+            }
+        }
+        #[cfg(feature = "gui")]
+        if let Some(overlay) = window.overlay.as_mut() {
+            if overlay.on_window_event(&window.window, &event) {
+                return Ok(WindowStatus::Open);
+            }
+        }
+        handler.event(Handle(&self.state), &event);
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if Some(key) == window.screenshot_key => {
+                window.screenshot = true;
+                window.window.request_redraw();
+            }
+            WindowEvent::CloseRequested => return Ok(WindowStatus::Closed),
+            WindowEvent::Resized(size) => {
+                self.state.size = size;
+                if let (Some(width), Some(height)) =
+                    (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+                {
+                    window.gl_surface.resize(&window.ctx, width, height);
+                }
+            }
+            WindowEvent::RedrawRequested => self.window_frame(window, handler)?,
+            _ => {}
+        }
+        Ok(WindowStatus::Open)
+    }
+
+    /// Draw and present one frame of the window loop.
+    fn window_frame(
+        &mut self,
+        window: &mut WindowLoop,
+        handler: &mut impl AppHandler,
+    ) -> Result<(), ErrKind> {
+        // Other windows may have drawn with the shared context since
+        window
+            .ctx
+            .make_current(&window.gl_surface)
+            .map_err(|source| ErrKind::InitError {
+                step: InitStep::MakeCurrent,
+                source,
+            })?;
+        let time = Instant::now();
+        let delta = self
+            .state
+            .last_frame
+            .replace(time)
+            .map(|last| time.duration_since(last));
+        let previous = self.state.time;
+        self.state.advance_time(delta.unwrap_or_default());
+        self.state.loop_phase = window
+            .loop_duration
+            .and_then(|duration| offline::loop_phase(self.state.time, duration));
+        // Follows the frame clock, which the audio playback may drive
+        let elapsed = self.state.time.saturating_sub(previous);
+        handler.update(Handle(&self.state), elapsed);
+        self.render_frame()?;
+        handler.draw(Handle(&self.state));
+        if std::mem::take(&mut window.screenshot) {
+            let saved = Handle(&self.state)
+                .capture_frame()
+                .and_then(|image| capture::save_screenshot(&image));
+            if let Ok(path) = &saved {
+                info!("Screenshot saved to {}", path.display());
+            }
+            log_error(saved);
+        }
+        #[cfg(feature = "gui")]
+        if let Some(overlay) = window.overlay.as_mut() {
+            self.state.profile_begin("overlay");
+            overlay.draw(&window.window, &mut self.state.params);
+            self.state.profile_end();
+        }
+        log_error(window.gl_surface.swap_buffers(&window.ctx));
+        if let Some(profiler) = self.state.profiler.as_mut() {
+            profiler.end_frame();
+        }
+        let cpu_time = time.elapsed();
+        self.record_frame(delta.unwrap_or(cpu_time), cpu_time);
+        Ok(())
+    }
+}
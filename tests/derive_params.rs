@@ -0,0 +1,86 @@
+use shimmer::params::{Param, ParamBlock, ParamKind, ParamValue, Params};
+
+#[derive(Debug, Clone, Copy, PartialEq, shimmer::Params)]
+struct Uniforms {
+    #[param(range = 0.0..=10.0, default = 2.0)]
+    speed: f32,
+    #[param(name = "tint_color", default = [1.0, 0.5, 0.0])]
+    tint: [f32; 3],
+    background: [f32; 4],
+    #[param(default = true)]
+    glow: bool,
+    fade: f32,
+    #[param(skip)]
+    time: f32,
+}
+
+fn registered() -> Params {
+    let mut params = Params::default();
+    for param in Uniforms::params() {
+        params.insert(param);
+    }
+    params
+}
+
+#[test]
+fn describes_every_field_but_skipped_ones() {
+    assert_eq!(
+        Uniforms::params(),
+        vec![
+            Param::float("speed", 2.0, 0.0..=10.0),
+            Param::color3("tint_color", [1.0, 0.5, 0.0]),
+            Param::color4("background", [1.0, 1.0, 1.0, 1.0]),
+            Param::toggle("glow", true),
+            Param::float("fade", 0.0, 0.0..=1.0),
+        ]
+    );
+    let fade = Uniforms::params().into_iter().find(|p| p.name == "fade");
+    assert_eq!(fade.map(|p| p.kind), Some(ParamKind::Slider(0.0..=1.0)));
+    assert!(Uniforms::params().iter().all(|p| p.name != "time"));
+}
+
+#[test]
+fn builds_defaults_from_empty_params() {
+    assert_eq!(
+        Uniforms::from_params(&Params::default()),
+        Uniforms {
+            speed: 2.0,
+            tint: [1.0, 0.5, 0.0],
+            background: [1.0, 1.0, 1.0, 1.0],
+            glow: true,
+            fade: 0.0,
+            time: 0.0,
+        }
+    );
+}
+
+#[test]
+fn round_trips_through_params() {
+    let uniforms = Uniforms {
+        speed: 7.5,
+        tint: [0.2, 0.4, 0.6],
+        background: [0.0, 0.0, 0.0, 0.5],
+        glow: false,
+        fade: 0.25,
+        time: 3.0,
+    };
+    let mut params = registered();
+    assert!(uniforms.write_params(&mut params).is_ok());
+    assert_eq!(
+        params.get::<ParamValue>("tint_color"),
+        Some(ParamValue::Vec3([0.2, 0.4, 0.6]))
+    );
+    assert_eq!(
+        Uniforms::from_params(&params),
+        Uniforms {
+            time: 0.0,
+            ..uniforms
+        }
+    );
+}
+
+#[test]
+fn writing_unregistered_params_fails() {
+    let uniforms = Uniforms::from_params(&Params::default());
+    assert!(uniforms.write_params(&mut Params::default()).is_err());
+}